@@ -1,14 +1,19 @@
-use crate::physics::{Collider, CollisionSetup, Shape, StaticCollider, VerletObject};
+use crate::physics::{
+    Collider, CollisionSetup, Fluid, ForceField, Kinematic, KinematicPath, Layer, OneWayPlatform,
+    PhysicsMaterial, Shape, StaticCollider, VerletObject,
+};
 use crate::rope_shooting::Hookable;
 use crate::timer::{Finish, TimerStarter};
-use bevy::app::{App, FixedUpdate, Plugin, Startup};
+use bevy::app::{App, FixedPreUpdate, FixedUpdate, Plugin, Startup};
 use bevy::color::Srgba;
-use bevy::prelude::{Color, Commands, Component, IntoSystemConfigs, Transform, Vec2};
+use bevy::prelude::{Color, Commands, Component, IntoSystemConfigs, Quat, Query, Transform, Vec2};
 use bevy::reflect::erased_serde::__private::serde::{Deserialize, Serialize};
 use bevy::sprite::Sprite;
-use bevy::utils::default;
+use bevy::utils::{default, HashMap};
+use once_cell::sync::Lazy;
 use regex::Regex;
 use serde_wasm_bindgen::from_value;
+use std::sync::{Arc, Mutex};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
 
@@ -17,6 +22,7 @@ pub struct CollisionImportPlugin;
 impl Plugin for CollisionImportPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, get_colliders_system.before(CollisionSetup));
+        app.add_systems(FixedPreUpdate, follow_dom_system);
     }
 }
 
@@ -26,8 +32,8 @@ fn get_colliders_system(mut commands: Commands) {
     commands.spawn((
         StaticCollider,
         Collider {
-            layer: 1,
-            layer_mask: 1,
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
             trigger: true,
             shape: Shape::Box {
                 width: 8.0,
@@ -51,8 +57,8 @@ fn get_colliders_system(mut commands: Commands) {
     commands.spawn((
         StaticCollider,
         Collider {
-            layer: 1,
-            layer_mask: 1,
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
             trigger: true,
             shape: Shape::Box {
                 width: 8.0,
@@ -72,14 +78,105 @@ fn get_colliders_system(mut commands: Commands) {
         Sprite::from_color(Color::BLACK, Vec2::new(16.0, 16.0)),
         Transform::from_xyz(900.0, -250.0, 1.0),
     ));
+    let platform_pos = Vec2::new(1100.0, -500.0);
+    commands.spawn((
+        StaticCollider,
+        Collider {
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+            shape: Shape::Box {
+                width: 40.0,
+                height: 8.0,
+            },
+        },
+        Kinematic::new(KinematicPath::Sine {
+            center: platform_pos,
+            amplitude: Vec2::new(0.0, 150.0),
+            frequency: 0.2,
+        }),
+        Hookable,
+        VerletObject {
+            fixed: true,
+            position_current: platform_pos,
+            position_old: platform_pos,
+            ..default()
+        },
+        Sprite::from_color(Color::BLACK, Vec2::new(80.0, 16.0)),
+        Transform::from_xyz(platform_pos.x, platform_pos.y, 1.0),
+    ));
+    let ramp_pos = Vec2::new(250.0, -700.0);
+    let ramp_rotation: f32 = 0.4;
+    commands.spawn((
+        StaticCollider,
+        Collider {
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+            shape: Shape::OrientedBox {
+                width: 100.0,
+                height: 8.0,
+                rotation: ramp_rotation,
+            },
+        },
+        Hookable,
+        VerletObject {
+            fixed: true,
+            position_current: ramp_pos,
+            ..default()
+        },
+        Sprite::from_color(Color::BLACK, Vec2::new(200.0, 16.0)),
+        Transform::from_xyz(ramp_pos.x, ramp_pos.y, 1.0)
+            .with_rotation(Quat::from_rotation_z(ramp_rotation)),
+    ));
+    let (wind, wind_shape, wind_offset) = field_for_marker("💨").unwrap();
+    let wind_pos = Vec2::new(1300.0, -800.0) + wind_offset;
+    commands.spawn((
+        StaticCollider,
+        Collider {
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: true,
+            shape: wind_shape,
+        },
+        wind,
+        VerletObject {
+            fixed: true,
+            position_current: wind_pos,
+            ..default()
+        },
+        Sprite::from_color(Color::srgba(0.5, 0.7, 1.0, 0.2), Vec2::new(64.0, 256.0)),
+        Transform::from_xyz(wind_pos.x, wind_pos.y, 0.5),
+    ));
+    let pool_pos = Vec2::new(1000.0, -900.0);
+    commands.spawn((
+        StaticCollider,
+        Collider {
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: true,
+            shape: Shape::Box {
+                width: 150.0,
+                height: 60.0,
+            },
+        },
+        water(),
+        VerletObject {
+            fixed: true,
+            position_current: pool_pos,
+            ..default()
+        },
+        Sprite::from_color(Color::srgba(0.1, 0.3, 0.9, 0.4), Vec2::new(300.0, 120.0)),
+        Transform::from_xyz(pool_pos.x, pool_pos.y, 0.5),
+    ));
     for i in 0..=5 {
         for j in 0..=5 {
             let pos = Vec2::new(400.0 + (i as f32 * 100.0), -300.0 - (j as f32 * 100.0));
-            commands.spawn((
+            let mut col_ent = commands.spawn((
                 StaticCollider,
                 Collider {
-                    layer: 1,
-                    layer_mask: 1,
+                    layer: Layer::WORLD,
+                    layer_mask: Layer::ALL,
                     trigger: false,
                     shape: Shape::Box {
                         width: 15.0,
@@ -98,6 +195,9 @@ fn get_colliders_system(mut commands: Commands) {
                 Sprite::from_color(Color::BLACK, Vec2::new(30.0, 30.0)),
                 Transform::from_xyz(pos.x, pos.y, 1.0),
             ));
+            if (j == 0) {
+                col_ent.insert(OneWayPlatform);
+            }
         }
     }
 }
@@ -110,10 +210,58 @@ fn contains_special_chars(s: &str) -> bool {
     let re = Regex::new(r"[^a-zA-Z0-9]").unwrap();
     re.is_match(s)
 }
+
+//links are icy, headings are bouncy
+fn material_for_tag(tag: &str) -> Option<PhysicsMaterial> {
+    match tag {
+        "A" => Some(PhysicsMaterial {
+            friction: 0.01,
+            restitution: 0.0,
+        }),
+        "H1" | "H2" | "H3" => Some(PhysicsMaterial {
+            friction: 0.1,
+            restitution: 0.8,
+        }),
+        _ => None,
+    }
+}
+fn water() -> Fluid {
+    Fluid {
+        density: 1.5,
+        drag: 4.0,
+    }
+}
+
+//the field replaces the marker's own box, offset is from the marker to the center of the field
+fn field_for_marker(letter: &str) -> Option<(ForceField, Shape, Vec2)> {
+    match letter {
+        //wind blows upwards out of the marker
+        "💨" => Some((
+            ForceField::Wind {
+                acceleration: Vec2::Y * 4096.0,
+            },
+            Shape::Box {
+                width: 32.0,
+                height: 128.0,
+            },
+            Vec2::new(0.0, 128.0),
+        )),
+        "🧲" => Some((
+            ForceField::Radial { strength: 3072.0 },
+            Shape::Circle { radius: 192.0 },
+            Vec2::ZERO,
+        )),
+        _ => None,
+    }
+}
 #[cfg(target_arch = "wasm32")]
 fn get_colliders_system(mut commands: Commands) {
-    let colliders = get_colliders_rust();
+    spawn_page_colliders(&mut commands, get_colliders_rust());
+}
 
+//also used by the headless tests, which build the colliders from json
+#[cfg(any(target_arch = "wasm32", test))]
+pub(crate) fn spawn_page_colliders(commands: &mut Commands, colliders: Vec<TestCollider>) {
     for collider in colliders {
         let mid_x = (collider.right + collider.left) / 2.0;
         let mid_y = (-collider.top + -collider.bottom) / 2.0;
@@ -125,43 +273,165 @@ fn get_colliders_system(mut commands: Commands) {
             blue: collider.color.b / 255.0,
             alpha: 1.0,
         };
-        let is_trigger = collider.letter == "⏱" || collider.letter == "🏁";
-
-        let pos = Vec2::new(mid_x, mid_y);
-        let mut col_ent = commands.spawn((
-            StaticCollider,
-            Collider {
-                layer: 1,
-                layer_mask: 1,
-                trigger: is_trigger,
-                shape: Shape::Box {
-                    width: width,
-                    height: height,
+        let field = field_for_marker(collider.letter.as_str());
+        //the extension sends sections marked as water as 🌊 as well
+        let is_fluid = collider.letter == "🌊";
+        let is_trigger =
+            collider.letter == "⏱" || collider.letter == "🏁" || field.is_some() || is_fluid;
+        //css angles are clockwise, shape rotations counter-clockwise
+        let shape = if (collider.rotation == 0.0) {
+            Shape::Box {
+                width: width,
+                height: height,
+            }
+        } else {
+            Shape::OrientedBox {
+                width: width,
+                height: height,
+                rotation: -collider.rotation,
+            }
+        };
+
+        //big glyphs come with a mask of their outline, which is split into boxes
+        let plain = vec![(Vec2::new(mid_x, mid_y), shape)];
+        let parts = match (&field, &collider.mask) {
+            (Some((_, field_shape, offset)), _) => {
+                vec![(Vec2::new(mid_x, mid_y) + *offset, field_shape.clone())]
+            }
+            (None, Some(ref mask)) if (collider.rotation == 0.0) => {
+                let parts = glyph_parts(
+                    mask,
+                    Vec2::new(collider.left, -collider.top),
+                    Vec2::new(
+                        width * 2.0 / mask.columns as f32,
+                        height * 2.0 / mask.rows as f32,
+                    ),
+                );
+                //thin strokes can leave every cell empty, the character still has to be solid
+                if (parts.is_empty()) {
+                    plain
+                } else {
+                    parts
+                }
+            }
+            (None, _) => plain,
+        };
+
+        for (pos, shape) in parts {
+            let mut col_ent = commands.spawn((
+                StaticCollider,
+                Collider {
+                    layer: Layer::WORLD,
+                    layer_mask: Layer::ALL,
+                    trigger: is_trigger,
+                    shape: shape,
                 },
-            },
-            VerletObject {
-                fixed: true,
-                position_current: pos,
-                ..default()
-            },
-            Colored {
-                color: Color::Srgba(color),
-            },
-            // Sprite::from_color(Color::WHITE, Vec2::new(width * 2.0, height * 2.0)),
-            Transform::from_xyz(pos.x, pos.y, 1.0),
-        ));
+                VerletObject {
+                    fixed: true,
+                    position_current: pos,
+                    ..default()
+                },
+                Colored {
+                    color: Color::Srgba(color),
+                },
+                // Sprite::from_color(Color::WHITE, Vec2::new(width * 2.0, height * 2.0)),
+                Transform::from_xyz(pos.x, pos.y, 1.0),
+            ));
 
-        if !contains_special_chars(collider.letter.as_str()) {
-            col_ent.insert(Hookable);
-        }
-        if collider.letter == "⏱" {
-            col_ent.insert(TimerStarter);
+            if !contains_special_chars(collider.letter.as_str()) {
+                col_ent.insert(Hookable);
+            }
+            if collider.letter == "⏱" {
+                col_ent.insert(TimerStarter);
+            }
+
+            if collider.letter == "🏁" {
+                col_ent.insert(Finish);
+            }
+
+            if let Some(material) = material_for_tag(collider.tag.as_str()) {
+                col_ent.insert(material);
+            }
+
+            if let Some((ref force_field, _, _)) = field {
+                col_ent.insert(force_field.clone());
+            }
+
+            if (is_fluid) {
+                col_ent.insert(water());
+            }
+
+            if (collider.one_way && !is_trigger) {
+                col_ent.insert(OneWayPlatform);
+            }
+
+            if let Some(ref element) = collider.element {
+                let element_pos = Vec2::new(element.left, -element.top);
+                col_ent.insert((
+                    Kinematic::new(KinematicPath::Follow { target: pos }),
+                    DomFollower {
+                        element: element.id,
+                        offset: pos - element_pos,
+                    },
+                ));
+            }
         }
+    }
+}
 
-        if collider.letter == "🏁" {
-            col_ent.insert(Finish);
+//boxes covering the filled cells of the mask, top_left is in game coordinates
+fn glyph_parts(mask: &GlyphMaskDTO, top_left: Vec2, cell_size: Vec2) -> Vec<(Vec2, Shape)> {
+    mask_rectangles(mask)
+        .into_iter()
+        .map(|(column, row, columns, rows)| {
+            let size = Vec2::new(columns as f32, rows as f32) * cell_size;
+            let corner = top_left + Vec2::new(column as f32, -(row as f32)) * cell_size;
+            let pos = corner + Vec2::new(size.x, -size.y) / 2.0;
+            (
+                pos,
+                Shape::Box {
+                    width: size.x / 2.0,
+                    height: size.y / 2.0,
+                },
+            )
+        })
+        .collect()
+}
+
+//greedily grows filled cells into rectangles, first to the right then down
+//returns (column, row, columns, rows) with rows counted from the top
+fn mask_rectangles(mask: &GlyphMaskDTO) -> Vec<(usize, usize, usize, usize)> {
+    let filled =
+        |column: usize, row: usize| mask.cells.get(row * mask.columns + column) == Some(&1);
+    let mut used = vec![false; mask.columns * mask.rows];
+    let mut rectangles = vec![];
+    for row in 0..mask.rows {
+        for column in 0..mask.columns {
+            if (!filled(column, row) || used[row * mask.columns + column]) {
+                continue;
+            }
+            let free = |column: usize, row: usize| {
+                filled(column, row) && !used[row * mask.columns + column]
+            };
+            let mut columns = 1;
+            while (column + columns < mask.columns && free(column + columns, row)) {
+                columns += 1;
+            }
+            let mut rows = 1;
+            while (row + rows < mask.rows
+                && (column..column + columns).all(|c| free(c, row + rows)))
+            {
+                rows += 1;
+            }
+            for r in row..row + rows {
+                for c in column..column + columns {
+                    used[r * mask.columns + c] = true;
+                }
+            }
+            rectangles.push((column, row, columns, rows));
         }
     }
+    rectangles
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -171,14 +441,71 @@ struct ColorDTO {
     b: f32,
     a: f32,
 }
+//the animated element a collider belongs to and where it was when the colliders were read
+#[derive(Serialize, Deserialize, Debug)]
+struct ElementDTO {
+    id: u32,
+    left: f32,
+    top: f32,
+}
+//coverage of the glyph, row by row from the top, 1 for filled cells
+#[derive(Serialize, Deserialize, Debug)]
+struct GlyphMaskDTO {
+    columns: usize,
+    rows: usize,
+    cells: Vec<u8>,
+}
 #[derive(Serialize, Deserialize, Debug)]
-struct TestCollider {
+pub(crate) struct TestCollider {
     top: f32,
     bottom: f32,
     right: f32,
     left: f32,
     letter: String,
     color: ColorDTO,
+    #[serde(default)]
+    tag: String,
+    //running text the player can jump through from below
+    #[serde(default)]
+    one_way: bool,
+    #[serde(default)]
+    element: Option<ElementDTO>,
+    //clockwise css rotation in radians, the edges describe the unrotated box around its center
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    mask: Option<GlyphMaskDTO>,
+}
+
+//keeps a kinematic collider at the same offset from an animated DOM element
+#[derive(Component)]
+pub struct DomFollower {
+    pub element: u32,
+    pub offset: Vec2,
+}
+
+static PENDING_ELEMENT_POSITIONS: Lazy<Arc<Mutex<HashMap<u32, Vec2>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
+
+//page coordinates of the top left corner, called by the extension whenever the element moves
+#[wasm_bindgen]
+pub fn set_element_pos(id: u32, left: f32, top: f32) {
+    let mut positions = PENDING_ELEMENT_POSITIONS.lock().unwrap();
+    positions.insert(id, Vec2::new(left, -top));
+}
+
+fn follow_dom_system(mut follower_query: Query<(&DomFollower, &mut Kinematic)>) {
+    let positions: HashMap<u32, Vec2> = PENDING_ELEMENT_POSITIONS.lock().unwrap().drain().collect();
+    if (positions.is_empty()) {
+        return;
+    }
+    for (follower, mut kinematic) in follower_query.iter_mut() {
+        if let Some(element_pos) = positions.get(&follower.element) {
+            kinematic.path = KinematicPath::Follow {
+                target: *element_pos + follower.offset,
+            };
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -213,3 +540,50 @@ pub fn get_colliders_rust() -> Vec<TestCollider> {
         vec![]
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_splits_into_covering_rectangles() {
+        //an "o": a ring with a hole in the middle
+        let mask = GlyphMaskDTO {
+            columns: 3,
+            rows: 3,
+            cells: vec![1, 1, 1, 1, 0, 1, 1, 1, 1],
+        };
+        let rectangles = mask_rectangles(&mask);
+        let mut covered = vec![0; 9];
+        for (column, row, columns, rows) in rectangles {
+            for r in row..row + rows {
+                for c in column..column + columns {
+                    covered[r * 3 + c] += 1;
+                }
+            }
+        }
+        assert_eq!(covered, vec![1, 1, 1, 1, 0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn empty_glyph_mask_falls_back_to_a_box() {
+        let colliders: Vec<TestCollider> = serde_json::from_str(
+            r#"[{"top": 0.0, "bottom": 40.0, "left": 0.0, "right": 10.0, "letter": "l",
+                "color": {"r": 0.0, "g": 0.0, "b": 0.0, "a": 255.0},
+                "mask": {"columns": 2, "rows": 8, "cells": [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]}}]"#,
+        )
+        .unwrap();
+        let mut world = bevy::prelude::World::new();
+        spawn_page_colliders(&mut world.commands(), colliders);
+        world.flush();
+        let shapes: Vec<Shape> = world
+            .query::<&Collider>()
+            .iter(&world)
+            .map(|collider| collider.shape.clone())
+            .collect();
+        assert!(
+            matches!(shapes[..], [Shape::Box { width, height }] if (width == 5.0 && height == 20.0)),
+            "{shapes:?}"
+        );
+    }
+}
@@ -1,11 +1,11 @@
 use crate::collider_import::Colored;
-use crate::physics::{PhysicsSet, TrackCollision};
+use crate::physics::{CollisionStarted, PhysicsSet};
 use crate::Player;
 use bevy::app::{App, FixedUpdate, Plugin};
 use bevy::color::{Alpha, Color};
 use bevy::prelude::{
-    in_state, AppExtStates, IntoSystemConfigs, Luminance, NextState, Query, ResMut, Resource,
-    Sprite, States, With,
+    in_state, AppExtStates, Entity, EventReader, IntoSystemConfigs, Luminance, NextState, Query,
+    ResMut, Resource, Sprite, States, With,
 };
 
 pub struct ColorPickerPlugin;
@@ -38,15 +38,16 @@ pub struct GlobalColor {
     pub background_color: Color,
 }
 fn change_color(
-    player_query: Query<(&TrackCollision), With<Player>>,
+    mut started_events: EventReader<CollisionStarted>,
+    player_query: Query<Entity, With<Player>>,
     colored_query: Query<&Colored>,
     mut sprite_query: Query<&mut Sprite>,
     mut color_res: ResMut<GlobalColor>,
     mut pick_state: ResMut<NextState<ColorPickState>>,
 ) {
-    for (track_collision) in player_query.iter() {
-        if let Some(collision) = track_collision.collisions.keys().next() {
-            if let Ok(colored) = colored_query.get(*collision) {
+    for started in started_events.read() {
+        if (player_query.contains(started.entity)) {
+            if let Ok(colored) = colored_query.get(started.other) {
                 for mut sprite in sprite_query.iter_mut() {
                     sprite.color = colored.color.with_alpha(sprite.color.alpha());
                 }
@@ -0,0 +1,450 @@
+use crate::collider_import::{spawn_page_colliders, TestCollider};
+use crate::color_picker::GlobalColor;
+use crate::physics::{
+    BreakableStick, Collider, ContinuousCollision, Kinematic, KinematicPath, Layer, PhysicsConfig,
+    PhysicsPlugin, Position, Shape, StaticCollider, Stick, VerletObject,
+};
+use crate::rope_rendering::Rope;
+use crate::rope_shooting::{Hookable, RopeShooter, RopeShootingPlugin};
+use crate::spawn_player_bodies;
+use bevy::app::App;
+use bevy::input::ButtonInput;
+use bevy::math::Vec2;
+use bevy::prelude::{default, Color, Entity, MouseButton, Transform};
+use bevy::time::TimeUpdateStrategy;
+use bevy::MinimalPlugins;
+use std::time::Duration;
+
+//one app update advances exactly one fixed tick at bevy's default 64hz
+const TICK: Duration = Duration::from_micros(15625);
+
+//what the scripted player does on a given tick
+pub enum InputAction {
+    Aim(Vec2),
+    Press,
+    Release,
+}
+
+//runs the physics and rope shooting without a window, camera or renderer
+pub struct Harness {
+    pub app: App,
+    pub player: Entity,
+    pub hand: Entity,
+    mouse: Entity,
+    tick: u32,
+    script: Vec<(u32, InputAction)>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(PhysicsPlugin);
+        app.add_plugins(RopeShootingPlugin);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(TICK));
+        //the rope tests check stretch against a tighter bound than the game needs
+        app.insert_resource(PhysicsConfig {
+            stick_iterations: 4,
+            ..default()
+        });
+        app.init_resource::<ButtonInput<MouseButton>>();
+        app.insert_resource(GlobalColor {
+            color: Color::WHITE,
+            background_color: Color::BLACK,
+        });
+        let dummy = app.world_mut().spawn_empty().id();
+        Self {
+            app,
+            player: dummy,
+            hand: dummy,
+            mouse: dummy,
+            tick: 0,
+            script: vec![],
+        }
+    }
+
+    //statics have to exist before the first step, the collision tree is built on startup
+    pub fn spawn_box(&mut self, center: Vec2, half_size: Vec2, hookable: bool) -> Entity {
+        let mut platform = self.app.world_mut().spawn((
+            Transform::from_xyz(center.x, center.y, 0.0),
+            StaticCollider,
+            Collider {
+                trigger: false,
+                shape: Shape::Box {
+                    width: half_size.x,
+                    height: half_size.y,
+                },
+                layer: Layer::WORLD,
+                layer_mask: Layer::ALL,
+            },
+            VerletObject {
+                position_current: center,
+                position_old: center,
+                fixed: true,
+                ..default()
+            },
+        ));
+        if (hookable) {
+            platform.insert(Hookable);
+        }
+        platform.id()
+    }
+
+    //colliders in the json format the extension sends, before the first step like spawn_box
+    pub fn spawn_page(&mut self, json: &str) {
+        let colliders: Vec<TestCollider> = serde_json::from_str(json).unwrap();
+        let world = self.app.world_mut();
+        spawn_page_colliders(&mut world.commands(), colliders);
+        world.flush();
+    }
+
+    //the real player's bodies, the indicator and hook preview are left empty
+    pub fn spawn_player(&mut self, pos: Vec2) {
+        let world = self.app.world_mut();
+        self.mouse = world.spawn(Position { pos }).id();
+        let indicator = world.spawn_empty().id();
+        let hook_preview = world.spawn_empty().id();
+        let (player, hand) = spawn_player_bodies(
+            &mut world.commands(),
+            pos,
+            self.mouse,
+            indicator,
+            hook_preview,
+        );
+        world.flush();
+        self.player = player;
+        self.hand = hand;
+    }
+
+    //gives the player and its hand the same velocity
+    pub fn launch(&mut self, vel: Vec2) {
+        self.launch_body(self.player, vel);
+        self.launch_body(self.hand, vel);
+    }
+
+    pub fn launch_body(&mut self, entity: Entity, vel: Vec2) {
+        let world = self.app.world_mut();
+        let dt = TICK.as_secs_f32() / world.resource::<PhysicsConfig>().sub_steps as f32;
+        if let Some(mut obj) = world.get_mut::<VerletObject>(entity) {
+            obj.set_velocity(vel, dt);
+        }
+    }
+
+    pub fn at(&mut self, tick: u32, action: InputAction) -> &mut Self {
+        self.script.push((tick, action));
+        self
+    }
+
+    pub fn step(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            let (due, later): (Vec<_>, Vec<_>) = std::mem::take(&mut self.script)
+                .into_iter()
+                .partition(|(tick, _)| *tick == self.tick);
+            self.script = later;
+            for (_, action) in due {
+                self.apply(action);
+            }
+            self.app.update();
+            //without the input plugin nothing clears the just pressed state
+            self.app
+                .world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .clear();
+            self.tick += 1;
+        }
+    }
+
+    fn apply(&mut self, action: InputAction) {
+        let world = self.app.world_mut();
+        match action {
+            InputAction::Aim(pos) => {
+                if let Some(mut mouse) = world.get_mut::<Position>(self.mouse) {
+                    mouse.pos = pos;
+                }
+            }
+            InputAction::Press => world
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left),
+            InputAction::Release => world
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .release(MouseButton::Left),
+        }
+    }
+
+    pub fn position(&self, entity: Entity) -> Vec2 {
+        self.app
+            .world()
+            .get::<VerletObject>(entity)
+            .map(|obj| obj.position_current)
+            .unwrap_or(Vec2::NAN)
+    }
+
+    //current and rest length of every rope, summed over its sticks
+    pub fn rope_lengths(&mut self) -> Vec<(f32, f32)> {
+        let world = self.app.world_mut();
+        let ropes: Vec<Vec<Entity>> = world
+            .query::<&Rope>()
+            .iter(world)
+            .map(|rope| rope.sticks.clone())
+            .collect();
+        ropes
+            .iter()
+            .map(|sticks| {
+                sticks
+                    .iter()
+                    .filter_map(|stick| world.get::<Stick>(*stick))
+                    .map(|stick| {
+                        let a = world.get::<VerletObject>(stick.ent1).unwrap();
+                        let b = world.get::<VerletObject>(stick.ent2).unwrap();
+                        (
+                            (a.position_current - b.position_current).length(),
+                            stick.length,
+                        )
+                    })
+                    .fold((0.0, 0.0), |(current, rest), (c, r)| {
+                        (current + c, rest + r)
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_resting_on_box_stays_put() {
+        let mut harness = Harness::new();
+        harness.spawn_box(Vec2::new(0.0, -20.0), Vec2::new(100.0, 10.0), false);
+        harness.spawn_player(Vec2::new(0.0, 0.0));
+        harness.at(0, InputAction::Aim(Vec2::new(0.0, 20.0)));
+        harness.step(128);
+        let settled = harness.position(harness.player);
+        harness.step(256);
+        let after = harness.position(harness.player);
+        assert!(
+            (after - settled).length() < 0.5,
+            "{settled} moved to {after}"
+        );
+        //the circle reaches 8 below its center and the box top is at -10
+        assert!(after.y > -2.5, "sank into the box: {after}");
+    }
+
+    //a page text line at game y -100..-116 that the player jumps through and then lands on
+    #[test]
+    fn player_jumps_through_one_way_text() {
+        let mut harness = Harness::new();
+        harness.spawn_page(
+            r#"[{"top": 100.0, "bottom": 116.0, "left": -100.0, "right": 100.0, "letter": "a",
+                "color": {"r": 0.0, "g": 0.0, "b": 0.0, "a": 255.0}, "tag": "P", "one_way": true}]"#,
+        );
+        harness.spawn_player(Vec2::new(0.0, -160.0));
+        harness.at(0, InputAction::Aim(Vec2::new(0.0, -80.0)));
+        harness.step(1);
+        harness.launch(Vec2::new(0.0, 900.0));
+        let mut highest = f32::MIN;
+        for _ in 0..128 {
+            harness.step(1);
+            highest = highest.max(harness.position(harness.player).y);
+        }
+        assert!(highest > -80.0, "blocked from below, peaked at {highest}");
+        let after = harness.position(harness.player);
+        //resting on top means the circle bottom touches -100
+        assert!(
+            (after.y + 92.0).abs() < 2.5,
+            "did not land on the line: {after}"
+        );
+    }
+
+    //one sub step per tick so the whole 64px of travel is a single sweep that hits the thin floor early
+    #[test]
+    fn fast_body_slides_on_after_a_swept_hit() {
+        let mut harness = Harness::new();
+        harness.app.insert_resource(PhysicsConfig {
+            sub_steps: 1,
+            ..default()
+        });
+        harness.spawn_box(Vec2::ZERO, Vec2::new(500.0, 2.0), false);
+        let start = Vec2::new(0.0, 6.5);
+        let ball = harness
+            .app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(start.x, start.y, 0.0),
+                ContinuousCollision,
+                Collider {
+                    trigger: false,
+                    shape: Shape::Circle { radius: 4.0 },
+                    layer: Layer::PLAYER,
+                    layer_mask: Layer::WORLD,
+                },
+                VerletObject {
+                    position_current: start,
+                    position_old: start - Vec2::new(64.0, -64.0),
+                    ..default()
+                },
+            ))
+            .id();
+        //the first update only starts the clock
+        harness.step(2);
+        let pos = harness.position(ball);
+        assert!(pos.y > 5.0, "went through the floor: {pos}");
+        assert!(pos.x > 48.0, "lost the travel after the hit: {pos}");
+        harness.step(1);
+        assert!(
+            harness.position(ball).x - pos.x > 48.0,
+            "lost the velocity along the floor"
+        );
+    }
+
+    fn sleeping_ball(harness: &mut Harness, pos: Vec2) -> Entity {
+        harness
+            .app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(pos.x, pos.y, 0.0),
+                Collider {
+                    trigger: false,
+                    shape: Shape::Circle { radius: 8.0 },
+                    layer: Layer::WORLD,
+                    layer_mask: Layer::ALL,
+                },
+                VerletObject {
+                    position_current: pos,
+                    position_old: pos,
+                    sleeping: true,
+                    ..default()
+                },
+            ))
+            .id()
+    }
+
+    fn is_sleeping(harness: &Harness, entity: Entity) -> bool {
+        harness
+            .app
+            .world()
+            .get::<VerletObject>(entity)
+            .unwrap()
+            .sleeping
+    }
+
+    //bodies only collide with statics, so a kinematic collider is what runs into sleepers
+    #[test]
+    fn moving_platform_wakes_a_sleeping_body() {
+        let mut harness = Harness::new();
+        let start = Vec2::new(-40.0, 0.0);
+        harness.app.world_mut().spawn((
+            Transform::from_xyz(start.x, start.y, 0.0),
+            StaticCollider,
+            Collider {
+                trigger: false,
+                shape: Shape::Box {
+                    width: 10.0,
+                    height: 10.0,
+                },
+                layer: Layer::WORLD,
+                layer_mask: Layer::ALL,
+            },
+            VerletObject {
+                position_current: start,
+                position_old: start,
+                fixed: true,
+                ..default()
+            },
+            Kinematic::new(KinematicPath::Linear {
+                from: start,
+                to: Vec2::new(200.0, 0.0),
+                period: 4.0,
+            }),
+        ));
+        let sleeper = sleeping_ball(&mut harness, Vec2::new(0.0, 0.0));
+        harness.step(4);
+        assert!(
+            is_sleeping(&harness, sleeper),
+            "woke before the platform arrived"
+        );
+        harness.step(24);
+        assert!(!is_sleeping(&harness, sleeper));
+        assert!(harness.position(sleeper).x > 1.0, "was not pushed");
+    }
+
+    #[test]
+    fn attaching_a_stick_wakes_a_sleeping_body() {
+        let mut harness = Harness::new();
+        let sleeper = sleeping_ball(&mut harness, Vec2::new(0.0, 0.0));
+        let anchor = harness
+            .app
+            .world_mut()
+            .spawn(VerletObject {
+                position_current: Vec2::new(0.0, 50.0),
+                position_old: Vec2::new(0.0, 50.0),
+                fixed: true,
+                ..default()
+            })
+            .id();
+        harness.step(2);
+        assert!(is_sleeping(&harness, sleeper));
+        harness.app.world_mut().spawn(Stick {
+            ent1: anchor,
+            ent2: sleeper,
+            length: 30.0,
+        });
+        harness.step(2);
+        assert!(!is_sleeping(&harness, sleeper));
+        //the stick pulls it up to its length instead of letting it fall
+        assert!(harness.position(sleeper).y > 1.0);
+    }
+
+    #[test]
+    fn snapping_a_middle_stick_drops_the_hook() {
+        let mut harness = Harness::new();
+        harness.spawn_box(Vec2::new(100.0, 200.0), Vec2::new(30.0, 10.0), true);
+        harness.spawn_player(Vec2::new(0.0, 0.0));
+        harness
+            .at(0, InputAction::Aim(Vec2::new(100.0, 200.0)))
+            .at(2, InputAction::Press);
+        harness.step(18);
+        let hook_point = |harness: &Harness| {
+            harness
+                .app
+                .world()
+                .get::<RopeShooter>(harness.hand)
+                .unwrap()
+                .hook_point
+        };
+        assert!(hook_point(&harness).is_some(), "the rope did not attach");
+
+        let world = harness.app.world_mut();
+        let sticks = world.query::<&Rope>().single(world).sticks.clone();
+        assert!(sticks.len() >= 3, "rope is too short: {}", sticks.len());
+        //breaks on the next tick no matter how long the stick is
+        world
+            .entity_mut(sticks[sticks.len() / 2])
+            .insert(BreakableStick { max_stretch: -1.0 });
+        harness.step(2);
+        assert_eq!(hook_point(&harness), None);
+    }
+
+    //ropes spawn at 90% of the shot distance, so the first ticks are skipped while they contract
+    #[test]
+    fn swinging_rope_keeps_its_length() {
+        let mut harness = Harness::new();
+        harness.spawn_box(Vec2::new(100.0, 200.0), Vec2::new(30.0, 10.0), true);
+        harness.spawn_player(Vec2::new(0.0, 0.0));
+        harness
+            .at(0, InputAction::Aim(Vec2::new(100.0, 200.0)))
+            .at(2, InputAction::Press);
+        harness.step(18);
+        assert_eq!(harness.rope_lengths().len(), 1, "the rope did not attach");
+        for _ in 0..256 {
+            harness.step(1);
+            for (current, rest) in harness.rope_lengths() {
+                assert!(
+                    (current - rest).abs() <= rest * 0.05,
+                    "rope is {current} long, rest length {rest}"
+                );
+            }
+        }
+    }
+}
@@ -1,16 +1,27 @@
 mod collider_import;
 mod color_picker;
+#[cfg(test)]
+mod headless;
+mod movement;
 mod physics;
+mod physics_debug;
+mod rope_rendering;
 mod rope_shooting;
+mod snapshot;
 mod timer;
 
 use crate::collider_import::CollisionImportPlugin;
 use crate::color_picker::{ColorPickerPlugin, GlobalColor};
+use crate::movement::{MovementPlugin, MovementState};
 use crate::physics::{
-    raycast, Collider, Collision, CollisionWorld, ConstantFriction, PhysicsPlugin, Position, Ray,
-    Shape, StaticCollider, Stick, SubStepSchedule, TrackCollision, VerletObject,
+    Collider, Collision, CollisionWorld, ConstantFriction, ContinuousCollision, Layer,
+    PhysicsPlugin, Position, Ray, Shape, StaticCollider, Stick, SubStepSchedule, TrackCollision,
+    VerletObject,
 };
-use crate::rope_shooting::{RopeShooter, RopeShootingPlugin};
+use crate::physics_debug::PhysicsDebugPlugin;
+use crate::rope_rendering::RopeRenderingPlugin;
+use crate::rope_shooting::{HookPreview, RopeShooter, RopeShootingPlugin};
+use crate::snapshot::SnapshotPlugin;
 use crate::timer::{StateValue, TimerPlugin};
 use bevy::app::{FixedUpdate, Startup};
 use bevy::color::Color;
@@ -71,7 +82,11 @@ fn main() {
     app.add_plugins(TimerPlugin);
     app.add_plugins(CollisionImportPlugin);
     app.add_plugins(PhysicsPlugin);
+    app.add_plugins(PhysicsDebugPlugin);
     app.add_plugins(RopeShootingPlugin);
+    app.add_plugins(RopeRenderingPlugin);
+    app.add_plugins(MovementPlugin);
+    app.add_plugins(SnapshotPlugin);
 
     #[cfg(target_arch = "wasm32")]
     app.insert_resource(ClearColor(Color::NONE));
@@ -97,6 +112,8 @@ struct RopeHolder {
     hand: Entity,
     mouse: Entity,
     indicator: Entity,
+    hook_preview: Entity,
+    //max speed of the hand in pixels per second
     power: f32,
 }
 
@@ -124,6 +141,88 @@ fn align_camera_origin(
     transform.translation.y = -window.height() / 2.0 - scroll_position.y as f32;
 }
 
+//the player and its hand without their sprites, shared with the headless harness.
+//returns (player, hand)
+pub(crate) fn spawn_player_bodies(
+    commands: &mut Commands,
+    p: Vec2,
+    mouse: Entity,
+    indicator: Entity,
+    hook_preview: Entity,
+) -> (Entity, Entity) {
+    let hand = commands
+        .spawn((
+            Transform::from_xyz(p.x, p.y, 0.0),
+            RopeShooter {
+                delete_old: true,
+                connections: vec![],
+                max_stretch: None,
+                hook_point: None,
+            },
+            Collider {
+                trigger: false,
+                shape: Shape::Circle { radius: 4.0 },
+                layer: Layer::PLAYER,
+                layer_mask: Layer::WORLD,
+            },
+            TrackCollision {
+                triggers: Default::default(),
+                collisions: HashMap::new(),
+                last: HashMap::new(),
+                last_triggers: Default::default(),
+            },
+            ConstantFriction,
+            ContinuousCollision,
+            VerletObject {
+                fixed: false,
+                position_old: p,
+                position_current: p,
+                acceleration: Vec2::ZERO,
+                friction: 0.8,
+                ..default()
+            },
+        ))
+        .id();
+
+    let player = commands
+        .spawn((
+            Transform::from_xyz(p.x, p.y, 0.0),
+            Player,
+            TrackCollision {
+                collisions: Default::default(),
+                last: Default::default(),
+                triggers: Default::default(),
+                last_triggers: Default::default(),
+            },
+            Collider {
+                trigger: false,
+                shape: Shape::Circle { radius: 8.0 },
+                layer: Layer::PLAYER,
+                layer_mask: Layer::WORLD,
+            },
+            ContinuousCollision,
+            RopeHolder {
+                power: 204.8,
+                hand,
+                mouse,
+                indicator,
+                hook_preview,
+            },
+            MovementState::default(),
+            VerletObject {
+                fixed: false,
+                position_old: p,
+                position_current: p,
+                acceleration: Vec2::ZERO,
+                //19 times heavier than the hand, so the hand does most of the moving
+                inverse_mass: 1.0 / 19.0,
+                ..default()
+            },
+        ))
+        .id();
+    (player, hand)
+}
+
 fn setup(mut commands: Commands, global_color: Res<GlobalColor>) {
     let mut camera = Camera2d;
 
@@ -131,37 +230,6 @@ fn setup(mut commands: Commands, global_color: Res<GlobalColor>) {
     let mut last_ent: Option<Entity> = None;
 
     let p = Vec2::new(800.0, -50.0);
-    let hand = commands.spawn((
-        Transform::from_xyz(p.x, p.y, 0.0),
-        RopeShooter {
-            delete_old: true,
-            connections: vec![],
-        },
-        Collider {
-            trigger: false,
-            shape: Shape::Circle { radius: 4.0 },
-            layer: 2,
-            layer_mask: 1,
-        },
-        TrackCollision {
-            triggers: Default::default(),
-            collisions: HashMap::new(),
-            last: HashMap::new(),
-            last_triggers: Default::default(),
-        },
-        ConstantFriction,
-        VerletObject {
-            fixed: false,
-            position_old: p,
-            position_current: p,
-            acceleration: Vec2::ZERO,
-            friction: 0.8,
-            ..default()
-        },
-        Sprite::from_color(global_color.color, Vec2::splat(8.0)),
-    ));
-    let hand_ent = hand.id();
-
     let mouse = commands.spawn((
         UnscrolledPosition { pos: p },
         Position { pos: p },
@@ -178,36 +246,22 @@ fn setup(mut commands: Commands, global_color: Res<GlobalColor>) {
     ));
     let indicator_id = indicator.id();
 
-    commands.spawn((
-        Transform::from_xyz(p.x, p.y, 0.0),
-        Player,
-        TrackCollision {
-            collisions: Default::default(),
-            last: Default::default(),
-            triggers: Default::default(),
-            last_triggers: Default::default(),
-        },
-        Collider {
-            trigger: false,
-            shape: Shape::Circle { radius: 8.0 },
-            layer: 2,
-            layer_mask: 1,
-        },
-        RopeHolder {
-            power: 0.4,
-            hand: hand_ent,
-            mouse: mouse_id,
-            indicator: indicator_id,
-        },
-        VerletObject {
-            fixed: false,
-            position_old: p,
-            position_current: p,
-            acceleration: Vec2::ZERO,
-            ..default()
-        },
-        Sprite::from_color(global_color.color, Vec2::splat(16.0)),
+    let hook_preview = commands.spawn((
+        HookPreview,
+        Transform::from_xyz(p.x, p.y, 2.0),
+        Sprite::from_color(global_color.color, Vec2::splat(6.0)),
+        Visibility::Hidden,
     ));
+    let hook_preview_id = hook_preview.id();
+
+    let (player, hand) =
+        spawn_player_bodies(&mut commands, p, mouse_id, indicator_id, hook_preview_id);
+    commands
+        .entity(hand)
+        .insert(Sprite::from_color(global_color.color, Vec2::splat(8.0)));
+    commands
+        .entity(player)
+        .insert(Sprite::from_color(global_color.color, Vec2::splat(16.0)));
     // let first_ent_id = first_ent.id();
     // last_ent = Some(first_ent_id);
     // for i in 1..=100 {
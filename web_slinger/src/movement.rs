@@ -0,0 +1,264 @@
+use crate::physics::{PhysicsConfig, PhysicsSet, Position, TrackCollision, VerletObject};
+use crate::rope_shooting::{rope_snapped_system, RopeReleased, RopeShooter};
+use crate::RopeHolder;
+use bevy::app::{App, FixedUpdate, Plugin, Update};
+use bevy::input::ButtonInput;
+use bevy::math::Vec2;
+use bevy::prelude::{
+    Commands, Component, EventReader, IntoSystemConfigs, KeyCode, Query, Res, ResMut, Resource,
+    Time,
+};
+use bevy::time::Fixed;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MovementSettings::default());
+        app.add_systems(
+            Update,
+            (
+                apply_pending_movement_settings,
+                release_boost_system,
+                air_dash_system,
+                start_zip_system.after(rope_snapped_system),
+            ),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                zip_system.before(PhysicsSet),
+                refill_dash_system.after(PhysicsSet),
+            ),
+        );
+    }
+}
+
+//speeds are in pixels per second, angles in radians
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MovementSettings {
+    //extra speed when the rope is released at the bottom of a swing
+    pub release_boost: f32,
+    //max angle between the rope and straight up that still counts as the bottom of the swing
+    pub release_window: f32,
+    //dashes available until the player touches something again
+    pub air_dashes: u32,
+    pub dash_speed: f32,
+    pub dash_key: KeyCode,
+    pub zip_speed: f32,
+    pub zip_stop_distance: f32,
+    pub zip_key: KeyCode,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        MovementSettings {
+            release_boost: 256.0,
+            release_window: 0.5,
+            air_dashes: 1,
+            dash_speed: 1024.0,
+            dash_key: KeyCode::ShiftLeft,
+            zip_speed: 1280.0,
+            zip_stop_distance: 24.0,
+            zip_key: KeyCode::KeyE,
+        }
+    }
+}
+
+impl MovementSettings {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    //a zip that never moves would never finish, the window can't be wider than a half turn
+    pub fn is_valid(&self) -> bool {
+        let non_negative = |value: f32| value.is_finite() && value >= 0.0;
+        non_negative(self.release_boost)
+            && non_negative(self.release_window)
+            && self.release_window <= std::f32::consts::PI
+            && non_negative(self.dash_speed)
+            && self.zip_speed.is_finite()
+            && self.zip_speed > 0.0
+            && non_negative(self.zip_stop_distance)
+    }
+}
+
+static PENDING_MOVEMENT_SETTINGS: Lazy<Arc<Mutex<Option<MovementSettings>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+//invalid settings are rejected and the current ones are kept
+#[wasm_bindgen]
+pub fn set_movement_settings_json(json: &str) -> bool {
+    let Ok(settings) = MovementSettings::from_json(json) else {
+        return false;
+    };
+    if (!settings.is_valid()) {
+        return false;
+    }
+    if let Ok(mut pending) = PENDING_MOVEMENT_SETTINGS.lock() {
+        *pending = Some(settings);
+    }
+    true
+}
+
+fn apply_pending_movement_settings(mut settings: ResMut<MovementSettings>) {
+    if let Ok(mut pending) = PENDING_MOVEMENT_SETTINGS.lock() {
+        if let Some(new_settings) = pending.take() {
+            *settings = new_settings;
+        }
+    }
+}
+
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
+pub struct MovementState {
+    pub dashes_left: u32,
+    pub zip_target: Option<Vec2>,
+    zip_last_distance: f32,
+}
+
+fn release_boost_system(
+    mut released_events: EventReader<RopeReleased>,
+    mut player_query: Query<(&mut VerletObject, &RopeHolder)>,
+    settings: Res<MovementSettings>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    let dt = config.sub_step_dt(&time);
+    for released in released_events.read() {
+        for (mut verlet_object, rope_holder) in player_query.iter_mut() {
+            if (rope_holder.hand != released.shooter) {
+                continue;
+            }
+            let to_hook = released.hook_point - verlet_object.position_current;
+            if (to_hook == Vec2::ZERO || to_hook.angle_to(Vec2::Y).abs() > settings.release_window)
+            {
+                continue;
+            }
+            let vel = verlet_object.velocity(dt);
+            if (vel == Vec2::ZERO) {
+                continue;
+            }
+            verlet_object.set_velocity(vel + vel.normalize() * settings.release_boost, dt);
+        }
+    }
+}
+
+fn air_dash_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<MovementSettings>,
+    mut player_query: Query<(
+        &mut VerletObject,
+        &mut MovementState,
+        &RopeHolder,
+        &TrackCollision,
+    )>,
+    mouse_pos_query: Query<&Position>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    if (!keys.just_pressed(settings.dash_key)) {
+        return;
+    }
+    let dt = config.sub_step_dt(&time);
+    for (mut verlet_object, mut state, rope_holder, track_collision) in player_query.iter_mut() {
+        if (state.dashes_left == 0 || !track_collision.collisions.is_empty()) {
+            continue;
+        }
+        let Ok(mouse_pos) = mouse_pos_query.get(rope_holder.mouse) else {
+            continue;
+        };
+        let dir = (mouse_pos.pos - verlet_object.position_current).normalize_or_zero();
+        if (dir == Vec2::ZERO) {
+            continue;
+        }
+        state.dashes_left -= 1;
+        state.zip_target = None;
+        verlet_object.set_velocity(dir * settings.dash_speed, dt);
+    }
+}
+
+fn refill_dash_system(
+    mut player_query: Query<(&mut MovementState, &TrackCollision)>,
+    settings: Res<MovementSettings>,
+) {
+    for (mut state, track_collision) in player_query.iter_mut() {
+        if (!track_collision.collisions.is_empty()) {
+            state.dashes_left = settings.air_dashes;
+        }
+    }
+}
+
+fn start_zip_system(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<MovementSettings>,
+    mut player_query: Query<(&mut MovementState, &RopeHolder)>,
+    mut shooter_query: Query<&mut RopeShooter>,
+) {
+    if (!keys.just_pressed(settings.zip_key)) {
+        return;
+    }
+    for (mut state, rope_holder) in player_query.iter_mut() {
+        let Ok(mut shooter) = shooter_query.get_mut(rope_holder.hand) else {
+            continue;
+        };
+        let Some(hook_point) = shooter.hook_point else {
+            continue;
+        };
+        state.zip_target = Some(hook_point);
+        state.zip_last_distance = f32::INFINITY;
+        shooter.release(&mut commands);
+    }
+}
+
+fn zip_system(
+    mut player_query: Query<(&mut VerletObject, &mut MovementState)>,
+    settings: Res<MovementSettings>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    let dt = config.sub_step_dt(&time);
+    for (mut verlet_object, mut state) in player_query.iter_mut() {
+        let Some(target) = state.zip_target else {
+            continue;
+        };
+        let diff = target - verlet_object.position_current;
+        let dist = diff.length();
+        //stop when arrived or when something blocks the way
+        if (dist < settings.zip_stop_distance || dist >= state.zip_last_distance) {
+            state.zip_target = None;
+            continue;
+        }
+        state.zip_last_distance = dist;
+        verlet_object.set_velocity(diff / dist * settings.zip_speed, dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_movement_settings_are_rejected() {
+        assert!(MovementSettings::default().is_valid());
+        for json in [
+            r#"{"zip_speed": 0.0}"#,
+            r#"{"dash_speed": -1.0}"#,
+            r#"{"release_window": 4.0}"#,
+            r#"{"air_dashes": -1}"#,
+            r#"not json"#,
+        ] {
+            assert!(!set_movement_settings_json(json), "{json} was accepted");
+        }
+        assert!(PENDING_MOVEMENT_SETTINGS.lock().unwrap().is_none());
+        assert!(set_movement_settings_json(r#"{"air_dashes": 2}"#));
+        let pending = PENDING_MOVEMENT_SETTINGS.lock().unwrap().take().unwrap();
+        assert_eq!(pending.air_dashes, 2);
+        assert_eq!(pending.dash_speed, 1024.0);
+    }
+}
@@ -1,16 +1,23 @@
 use crate::RopeHolder;
 use bevy::app::{App, FixedUpdate, Plugin, Startup};
+use bevy::ecs::component::ComponentId;
 use bevy::ecs::schedule::ScheduleLabel;
+use bevy::ecs::system::SystemParam;
+use bevy::ecs::world::DeferredWorld;
 use bevy::input::mouse::MouseMotion;
 use bevy::math::{Vec2, Vec3};
 use bevy::prelude::{
-    Camera, Commands, Component, Entity, EventReader, FixedPreUpdate, FloatExt, GlobalTransform,
-    IntoSystemConfigs, Query, Res, ResMut, Resource, Single, Sprite, Srgba, SystemSet, Time,
-    Transform, Update, Window, With, Without, World,
+    Commands, Component, Entity, Event, EventReader, EventWriter, FixedPreUpdate, FloatExt,
+    IntoSystemConfigs, ParamSet, Query, Res, ResMut, Resource, SystemSet, Time, Transform, With,
+    Without, World,
 };
-use bevy::utils::hashbrown::HashSet;
-use bevy::utils::HashMap;
+use bevy::time::Fixed;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::from_value;
 use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::JsValue;
 
 #[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct SubStepSchedule;
@@ -20,7 +27,7 @@ pub struct PhysicsPlugin;
 trait Verlet {
     fn accelerate(&mut self, acc: Vec2);
 }
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct VerletObject {
     pub position_current: Vec2,
     pub position_old: Vec2,
@@ -28,6 +35,14 @@ pub struct VerletObject {
     pub fixed: bool,
     pub drag: f32,
     pub friction: f32,
+    //0.0 makes the body immovable by constraints and collisions
+    pub inverse_mass: f32,
+    //sleeping objects are skipped by the solver until something wakes them
+    pub sleeping: bool,
+    //ticks in a row spent slower than PhysicsConfig::sleep_speed
+    pub still_ticks: u32,
+    //added to drag for the current tick, e.g. by fluids
+    pub extra_drag: f32,
 }
 
 impl Default for VerletObject {
@@ -37,25 +52,179 @@ impl Default for VerletObject {
             position_old: Vec2::ZERO,
             acceleration: Vec2::ZERO,
             fixed: false,
-            drag: 0.001,
+            drag: 0.512,
             friction: 0.1,
+            inverse_mass: 1.0,
+            sleeping: false,
+            still_ticks: 0,
+            extra_drag: 0.0,
         };
     }
 }
 
+impl VerletObject {
+    pub fn effective_inverse_mass(&self) -> f32 {
+        if self.fixed {
+            0.0
+        } else {
+            self.inverse_mass
+        }
+    }
+
+    pub fn velocity(&self, dt: f32) -> Vec2 {
+        (self.position_current - self.position_old) / dt
+    }
+
+    pub fn set_velocity(&mut self, vel: Vec2, dt: f32) {
+        self.position_old = self.position_current - vel * dt;
+        self.wake();
+    }
+
+    pub fn wake(&mut self) {
+        self.sleeping = false;
+        self.still_ticks = 0;
+    }
+
+    //drag is the fraction of velocity lost per second (exponential decay)
+    fn integrate(&mut self, dt: f32) {
+        let vel = (self.position_current - self.position_old)
+            * (-(self.drag + self.extra_drag) * dt).exp();
+        self.position_old = self.position_current;
+        self.position_current += vel + self.acceleration * dt * dt;
+    }
+}
+
 impl Verlet for VerletObject {
     fn accelerate(&mut self, acc: Vec2) {
         self.acceleration += acc;
     }
 }
 
+//adding or removing a stick wakes both ends
 #[derive(Component)]
+#[component(on_add = wake_stick_ends, on_remove = wake_stick_ends)]
 pub struct Stick {
     pub ent1: Entity,
     pub ent2: Entity,
     pub length: f32,
 }
 
+fn wake_stick_ends(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let Some(stick) = world.get::<Stick>(entity) else {
+        return;
+    };
+    let ends = [stick.ent1, stick.ent2];
+    for end in ends {
+        if let Some(mut verlet_object) = world.get_mut::<VerletObject>(end) {
+            verlet_object.wake();
+        }
+    }
+}
+
+//snaps the stick once it is stretched by more than max_stretch (0.5 = 150% of its length)
+#[derive(Component)]
+pub struct BreakableStick {
+    pub max_stretch: f32,
+}
+
+#[derive(Event)]
+pub struct RopeSnapped {
+    pub stick: Entity,
+    pub ent1: Entity,
+    pub ent2: Entity,
+    pub position: Vec2,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PhysicsConfig {
+    //in pixels per second squared
+    pub gravity: Vec2,
+    pub sub_steps: u32,
+    //max distance between the player and the hand
+    pub hand_leash_length: f32,
+    //how much of the hand's velocity into a surface gets absorbed by the player
+    pub cushion_factor: f32,
+    //in pixels per second, bodies slower than this for sleep_ticks fall asleep
+    pub sleep_speed: f32,
+    pub sleep_ticks: u32,
+    //passes over all sticks per sub step, more keep heavy loads from stretching ropes
+    pub stick_iterations: u32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            gravity: -Vec2::Y * 2621.44,
+            sub_steps: 8,
+            hand_leash_length: 64.0,
+            cushion_factor: 0.2,
+            sleep_speed: 4.0,
+            sleep_ticks: 32,
+            stick_iterations: 1,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    pub fn sub_step_dt(&self, time: &Time<Fixed>) -> f32 {
+        time.timestep().as_secs_f32() / self.sub_steps as f32
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    //zero sub steps divide dt by zero, negative lengths and speeds make no sense
+    pub fn is_valid(&self) -> bool {
+        let non_negative = |value: f32| value.is_finite() && value >= 0.0;
+        self.sub_steps > 0
+            && self.stick_iterations > 0
+            && self.gravity.is_finite()
+            && non_negative(self.hand_leash_length)
+            && non_negative(self.cushion_factor)
+            && non_negative(self.sleep_speed)
+    }
+}
+
+//invalid configs are rejected and the current one is kept
+fn queue_physics_config(config: PhysicsConfig) -> bool {
+    if (!config.is_valid()) {
+        return false;
+    }
+    if let Ok(mut pending) = PENDING_PHYSICS_CONFIG.lock() {
+        *pending = Some(config);
+    }
+    true
+}
+
+static PENDING_PHYSICS_CONFIG: once_cell::sync::Lazy<Arc<Mutex<Option<PhysicsConfig>>>> =
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+#[wasm_bindgen]
+pub fn set_physics_config(config: JsValue) -> bool {
+    let Ok(config) = from_value::<PhysicsConfig>(config) else {
+        return false;
+    };
+    queue_physics_config(config)
+}
+
+#[wasm_bindgen]
+pub fn set_physics_config_json(json: &str) -> bool {
+    let Ok(config) = PhysicsConfig::from_json(json) else {
+        return false;
+    };
+    queue_physics_config(config)
+}
+
+fn apply_pending_physics_config(mut config: ResMut<PhysicsConfig>) {
+    if let Ok(mut pending) = PENDING_PHYSICS_CONFIG.lock() {
+        if let Some(new_config) = pending.take() {
+            *config = new_config;
+        }
+    }
+}
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CollisionSetup;
 
@@ -64,14 +233,12 @@ pub struct PhysicsSet;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedPreUpdate, (reset_forces, reset_collisions));
+        app.init_resource::<PhysicsConfig>();
         app.add_systems(
-            Startup,
-            (
-                // display_collision_tree.after(build_collision_tree),
-                (build_collision_tree).in_set(CollisionSetup),
-            ),
+            FixedPreUpdate,
+            (reset_forces, reset_collisions, apply_pending_physics_config),
         );
+        app.add_systems(Startup, ((build_collision_tree).in_set(CollisionSetup),));
 
         // app.add_systems(Update, (adjust_drag_system,));
         // app.add_systems(Update, adjust_power_system);
@@ -79,11 +246,32 @@ impl Plugin for PhysicsPlugin {
             FixedUpdate,
             (
                 apply_gravity.before(run_sub_steps),
+                apply_fluids.after(apply_gravity).before(run_sub_steps),
+                (
+                    plan_kinematic_system,
+                    (refit_collision_tree, wake_near_kinematic_system),
+                )
+                    .chain()
+                    .before(run_sub_steps),
                 // apply_drag_adjustment.before(run_sub_steps),
                 // adjust_drag_active_system.after(run_sub_steps),
                 run_sub_steps.in_set(PhysicsSet),
+                break_sticks_system.in_set(PhysicsSet).after(run_sub_steps),
+                collision_events_system
+                    .in_set(PhysicsSet)
+                    .after(run_sub_steps),
+                sleep_system.in_set(PhysicsSet).after(run_sub_steps),
+                splash_system
+                    .in_set(PhysicsSet)
+                    .after(collision_events_system),
             ),
         );
+        app.add_event::<RopeSnapped>();
+        app.add_event::<CollisionStarted>();
+        app.add_event::<CollisionEnded>();
+        app.add_event::<TriggerEntered>();
+        app.add_event::<TriggerExited>();
+        app.add_event::<Splash>();
         app.insert_resource(CollisionWorld { kd_tree: None });
 
         app.add_systems(
@@ -98,13 +286,21 @@ impl Plugin for PhysicsPlugin {
                 cushion_system
                     .before(static_collision_system)
                     .after(update_verlet_position),
+                move_kinematic_system
+                    .before(update_verlet_position)
+                    .before(mouse_constraint_system),
+                continuous_collision_system
+                    .after(update_verlet_position)
+                    .after(mouse_constraint_system)
+                    .before(static_collision_system),
             ),
         );
     }
 }
 
 fn run_sub_steps(world: &mut World) {
-    for i in 0..8 {
+    let sub_steps = world.resource::<PhysicsConfig>().sub_steps;
+    for i in 0..sub_steps {
         world.run_schedule(SubStepSchedule);
     }
 }
@@ -131,8 +327,9 @@ pub fn line_line_intersection(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2, a_inf: boo
     let d = (d0.dot(a1 - b1));
     let e = (d1.dot(a1 - b1));
 
+    //relative to the segment lengths, so tiny and huge segments get the same parallel check
     let num = a * c - b * b;
-    if num.abs() < 1e-6 {
+    if (num.abs() <= 1e-6 * a * c) {
         return (false, f32::INFINITY);
     }
 
@@ -160,40 +357,44 @@ impl AABB {
             && min_y_a <= max_y_b
             && max_y_a >= min_y_b);
     }
+    //slab test, the distance is where the ray enters the box and 0 if it starts inside,
+    //so it never overshoots the hits of the objects in the box
     fn intersect_ray(&self, ray: &Ray) -> (bool, f32) {
-        let min_x = self.pos.x;
-        let min_y = self.pos.y;
-        let max_x = self.pos.x + self.size.x;
-        let max_y = self.pos.y + self.size.y;
-
-        let cor_a = Vec2::new(min_x, min_y);
-        let cor_b = Vec2::new(min_x, max_y);
-        let cor_c = Vec2::new(max_x, max_y);
-        let cor_d = Vec2::new(max_x, min_y);
-
-        let (a_hit, a_dist) =
-            line_line_intersection(ray.origin, ray.origin + ray.direction, cor_a, cor_b, true);
-
-        let (b_hit, b_dist) =
-            line_line_intersection(ray.origin, ray.origin + ray.direction, cor_b, cor_c, true);
-
-        let (c_hit, c_dist) =
-            line_line_intersection(ray.origin, ray.origin + ray.direction, cor_c, cor_d, true);
-
-        let (d_hit, d_dist) =
-            line_line_intersection(ray.origin, ray.origin + ray.direction, cor_d, cor_a, true);
-
-        return (
-            a_hit || b_hit || c_hit || d_hit,
-            a_dist.min(b_dist).min(c_dist).min(d_dist),
-        );
+        if (!ray.origin.is_finite()
+            || !ray.direction.is_finite()
+            || ray.direction.length_squared() == 0.0)
+        {
+            return (false, f32::INFINITY);
+        }
+        let min = self.pos;
+        let max = self.pos + self.size;
+        let mut t_enter: f32 = 0.0;
+        let mut t_exit = f32::INFINITY;
+        for axis in 0..2 {
+            let origin = ray.origin[axis];
+            let direction = ray.direction[axis];
+            if (direction == 0.0) {
+                if (origin < min[axis] || origin > max[axis]) {
+                    return (false, f32::INFINITY);
+                }
+                continue;
+            }
+            let t1 = (min[axis] - origin) / direction;
+            let t2 = (max[axis] - origin) / direction;
+            t_enter = t_enter.max(t1.min(t2));
+            t_exit = t_exit.min(t1.max(t2));
+        }
+        if (t_enter > t_exit) {
+            return (false, f32::INFINITY);
+        }
+        (true, t_enter)
     }
 }
 pub struct KDNode {
-    bounding_box: AABB,
-    left_node: Option<Arc<Mutex<KDNode>>>,
-    right_node: Option<Arc<Mutex<KDNode>>>,
-    objects: Vec<Entity>,
+    pub bounding_box: AABB,
+    pub left_node: Option<Arc<Mutex<KDNode>>>,
+    pub right_node: Option<Arc<Mutex<KDNode>>>,
+    pub objects: Vec<Entity>,
 }
 
 #[derive(Clone)]
@@ -303,15 +504,15 @@ fn build_collision_tree(
     for (collider, verlet_obj, entity) in collider_query.iter() {
         let bounds: AABB;
         match collider.shape {
-            Shape::Circle { radius } => {
-                return;
-            }
             Shape::Box { width, height } => {
                 bounds = AABB {
                     pos: verlet_obj.position_current - Vec2::new(width, height),
                     size: Vec2::new(width * 2.0, height * 2.0),
                 };
             }
+            _ => {
+                bounds = collider.get_bounding_box(verlet_obj.position_current);
+            }
         }
         let obj = ColliderObj {
             bounding_box: bounds,
@@ -328,47 +529,6 @@ fn build_collision_tree(
     //call kd_tree() with all collider-bounding box pairs
 }
 
-fn display_sub_tree(node: &KDNode, commands: &mut Commands, depth: usize) {
-    if (depth > 5) {
-        return;
-    }
-    if (depth == 5) {
-        let color = Srgba {
-            red: 0.4,
-            green: 1.0,
-            blue: 1.0,
-            alpha: 0.01,
-        };
-        commands.spawn((
-            Transform::from_xyz(
-                node.bounding_box.pos.x + node.bounding_box.size.x / 2.0,
-                node.bounding_box.pos.y + node.bounding_box.size.y / 2.0,
-                5.0 + depth as f32 / 2.0,
-            ),
-            Sprite::from_color(
-                color,
-                Vec2::new(node.bounding_box.size.x, node.bounding_box.size.y),
-            ),
-        ));
-    }
-
-    if let Some(ref node) = node.left_node {
-        let kd_node = node.lock().unwrap();
-        display_sub_tree(&kd_node, commands, depth + 1);
-    }
-
-    if let Some(ref node) = node.right_node {
-        let kd_node = node.lock().unwrap();
-        display_sub_tree(&kd_node, commands, depth + 1);
-    }
-}
-fn display_collision_tree(mut commands: Commands, col_world: Res<CollisionWorld>) {
-    if let Some(ref node) = col_world.kd_tree {
-        let kd_node = node.lock().unwrap();
-        display_sub_tree(&kd_node, &mut commands, 0);
-    }
-}
-
 fn find_collision_entities(
     bounding_box: &AABB,
     tree: &Option<Arc<Mutex<KDNode>>>,
@@ -421,32 +581,82 @@ fn static_collision_system(
     >,
     kd_tree: Res<CollisionWorld>,
     constant_friction: Query<&ConstantFriction>,
+    kinematic_query: Query<&Kinematic>,
     static_collider_query: Query<(&Collider, &VerletObject, Entity), With<StaticCollider>>,
+    material_query: Query<&PhysicsMaterial>,
+    one_way_query: Query<&OneWayPlatform>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
 ) {
+    let dt = config.sub_step_dt(&time);
     for (collider_a, mut verlet_object_a, mut tracker, ent) in collider_query.iter_mut() {
+        if (verlet_object_a.sleeping) {
+            continue;
+        }
+        let material_a = material_query
+            .get(ent)
+            .ok()
+            .cloned()
+            .unwrap_or(PhysicsMaterial {
+                friction: verlet_object_a.friction,
+                restitution: 0.0,
+            });
         let bounding_box: AABB = collider_a.get_bounding_box(verlet_object_a.position_current);
         let mut colliders = vec![];
         find_collision_entities(&bounding_box, &kd_tree.kd_tree, &mut colliders);
         for col_ent in colliders {
             if let Ok((collider_b, verlet_object_b, ent)) = static_collider_query.get(col_ent) {
+                if (!collider_a.interacts_with(collider_b)) {
+                    continue;
+                }
                 let (collides, err, norm) =
                     calc_collision(&verlet_object_a, &verlet_object_b, collider_a, collider_b);
 
+                if (collides && one_way_query.get(ent).is_ok()) {
+                    if (!lands_on_one_way(
+                        &verlet_object_a,
+                        collider_a,
+                        &verlet_object_b,
+                        collider_b,
+                        norm,
+                    )) {
+                        continue;
+                    }
+                }
+
                 if (collides) {
+                    let material = match material_query.get(ent) {
+                        Ok(material_b) => material_a.combine(material_b),
+                        Err(_) => material_a.clone(),
+                    };
                     if (!collider_b.trigger) {
                         //todo: fix double friction
 
+                        //bounce and friction act relative to a moving collider
+                        let platform_step = kinematic_query
+                            .get(ent)
+                            .map(|kinematic| kinematic.step)
+                            .unwrap_or(Vec2::ZERO);
+                        verlet_object_a.position_old += platform_step;
+                        let vel = verlet_object_a.position_current - verlet_object_a.position_old;
                         verlet_object_a.position_current += err;
+                        apply_restitution(norm, vel, material.restitution, &mut verlet_object_a);
                         if (constant_friction.get(ent)).is_err() {
-                            apply_friction(norm, &mut verlet_object_a);
+                            apply_friction(norm, material.friction, dt, &mut verlet_object_a);
                         }
+                        verlet_object_a.position_old -= platform_step;
                     }
 
                     if let Some(mut tracker_a) = tracker.as_mut() {
+                        let collision = Collision {
+                            normal: norm,
+                            point: contact_point(&verlet_object_a, collider_a, norm),
+                            friction: material.friction,
+                        };
                         if (collider_b.trigger) {
-                            tracker_a.triggers.insert(ent);
+                            tracker_a.triggers.insert(ent, collision);
                         } else {
-                            tracker_a.collisions.insert(ent, Collision { normal: norm });
+                            tracker_a.collisions.insert(ent, collision);
                         }
                     }
                 }
@@ -459,16 +669,14 @@ fn collision_system(mut collider_query: Query<(&Collider, &mut VerletObject)>) {
     while let Some([(collider_a, mut verlet_object_a), (collider_b, mut verlet_object_b)]) =
         combinations.fetch_next()
     {
-        if (collider_a.layer_mask & collider_b.layer == 0
-            || collider_b.layer_mask & collider_a.layer == 0)
-        {
+        if (!collider_a.interacts_with(collider_b)) {
             continue;
         }
         let (collides, err, norm) =
             calc_collision(&verlet_object_a, &verlet_object_b, collider_a, collider_b);
         if (collides) {
-            let ma = if verlet_object_a.fixed { 0.0 } else { 1.0 };
-            let mb = if verlet_object_b.fixed { 0.0 } else { 1.0 };
+            let ma = verlet_object_a.effective_inverse_mass();
+            let mb = verlet_object_b.effective_inverse_mass();
             if (ma + mb <= 0.0) {
                 continue;
             }
@@ -478,33 +686,420 @@ fn collision_system(mut collider_query: Query<(&Collider, &mut VerletObject)>) {
     }
 }
 
-#[derive(Debug)]
+//width and height are half extents, rotation is counter-clockwise in radians
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Shape {
-    Circle { radius: f32 },
-    Box { width: f32, height: f32 },
+    Circle {
+        radius: f32,
+    },
+    Box {
+        width: f32,
+        height: f32,
+    },
+    OrientedBox {
+        width: f32,
+        height: f32,
+        rotation: f32,
+    },
+    //vertical segment of half_length rounded by radius
+    Capsule {
+        radius: f32,
+        half_length: f32,
+    },
+    //points are relative to the position and in counter-clockwise order
+    ConvexPolygon {
+        points: Vec<Vec2>,
+    },
+}
+
+impl Shape {
+    //every shape is a convex core (point, segment or polygon) grown by a radius
+    pub fn core(&self, pos: Vec2) -> (Vec<Vec2>, f32) {
+        match self {
+            Shape::Circle { radius } => (vec![pos], *radius),
+            Shape::Box { width, height } => {
+                (box_vertices(pos, Vec2::new(*width, *height), 0.0), 0.0)
+            }
+            Shape::OrientedBox {
+                width,
+                height,
+                rotation,
+            } => (
+                box_vertices(pos, Vec2::new(*width, *height), *rotation),
+                0.0,
+            ),
+            Shape::Capsule {
+                radius,
+                half_length,
+            } => (
+                vec![
+                    pos - Vec2::new(0.0, *half_length),
+                    pos + Vec2::new(0.0, *half_length),
+                ],
+                *radius,
+            ),
+            Shape::ConvexPolygon { points } => (points.iter().map(|p| pos + *p).collect(), 0.0),
+        }
+    }
+}
+
+fn box_vertices(pos: Vec2, half_size: Vec2, rotation: f32) -> Vec<Vec2> {
+    let rot = Vec2::from_angle(rotation);
+    [
+        Vec2::new(-half_size.x, -half_size.y),
+        Vec2::new(half_size.x, -half_size.y),
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+    ]
+    .iter()
+    .map(|corner| pos + rot.rotate(*corner))
+    .collect()
+}
+
+//rotates a ray into the frame of a shape centered at pos with the given rotation
+fn local_ray(ray: &Ray, pos: Vec2, rotation: f32) -> Ray {
+    let inv_rot = Vec2::from_angle(-rotation);
+    Ray {
+        origin: inv_rot.rotate(ray.origin - pos),
+        direction: inv_rot.rotate(ray.direction),
+    }
 }
 #[derive(Component)]
 pub struct StaticCollider;
 
+//a static collider that moves, times are in seconds and speeds in pixels per second
+#[derive(Clone, Debug)]
+pub enum KinematicPath {
+    //back and forth between from and to, period is one round trip
+    Linear {
+        from: Vec2,
+        to: Vec2,
+        period: f32,
+    },
+    Sine {
+        center: Vec2,
+        amplitude: Vec2,
+        frequency: f32,
+    },
+    //loops through the points, returning from the last to the first
+    Waypoints {
+        points: Vec<Vec2>,
+        speed: f32,
+    },
+    //target is set from outside, e.g. by an animated DOM element
+    Follow {
+        target: Vec2,
+    },
+}
+
+impl KinematicPath {
+    pub fn position_at(&self, time: f32) -> Vec2 {
+        match self {
+            KinematicPath::Linear { from, to, period } => {
+                //also catches a NaN period
+                if (!(*period > 0.0)) {
+                    return *from;
+                }
+                let phase = (time / period).rem_euclid(1.0);
+                let t = 1.0 - (phase * 2.0 - 1.0).abs();
+                from.lerp(*to, t)
+            }
+            KinematicPath::Sine {
+                center,
+                amplitude,
+                frequency,
+            } => *center + *amplitude * (time * frequency * std::f32::consts::TAU).sin(),
+            KinematicPath::Waypoints { points, speed } => {
+                let Some(first) = points.first() else {
+                    return Vec2::ZERO;
+                };
+                let segments: Vec<(Vec2, Vec2)> = points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .map(|(a, b)| (*a, *b))
+                    .collect();
+                let total: f32 = segments.iter().map(|(a, b)| a.distance(*b)).sum();
+                if (total <= 0.0) {
+                    return *first;
+                }
+                let mut dist = (time * speed).rem_euclid(total);
+                for (a, b) in segments {
+                    let length = a.distance(b);
+                    if (dist <= length) {
+                        return a.lerp(b, dist / length);
+                    }
+                    dist -= length;
+                }
+                *first
+            }
+            KinematicPath::Follow { target } => *target,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Kinematic {
+    pub path: KinematicPath,
+    pub time: f32,
+    //movement per sub step during the current tick
+    pub(crate) step: Vec2,
+}
+
+impl Kinematic {
+    pub fn new(path: KinematicPath) -> Self {
+        Kinematic {
+            path,
+            time: 0.0,
+            step: Vec2::ZERO,
+        }
+    }
+
+    //area covered by the collider while it moves during this tick
+    fn swept_bounds(
+        &self,
+        collider: &Collider,
+        verlet_object: &VerletObject,
+        sub_steps: u32,
+    ) -> AABB {
+        let end = verlet_object.position_current + self.step * sub_steps as f32;
+        combine_bounding_boxes(
+            collider.get_bounding_box(verlet_object.position_current),
+            collider.get_bounding_box(end),
+        )
+    }
+}
+
+fn plan_kinematic_system(
+    mut kinematic_query: Query<(&mut Kinematic, &VerletObject)>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    for (mut kinematic, verlet_object) in kinematic_query.iter_mut() {
+        kinematic.time += time.timestep().as_secs_f32();
+        let target = kinematic.path.position_at(kinematic.time);
+        kinematic.step = (target - verlet_object.position_current) / config.sub_steps as f32;
+    }
+}
+
+fn move_kinematic_system(mut kinematic_query: Query<(&Kinematic, &mut VerletObject)>) {
+    for (kinematic, mut verlet_object) in kinematic_query.iter_mut() {
+        verlet_object.position_old = verlet_object.position_current;
+        verlet_object.position_current += kinematic.step;
+    }
+}
+
+//grows the broadphase boxes to cover where the kinematic colliders move during this tick
+fn refit_collision_tree(
+    kinematic_query: Query<(&Kinematic, &Collider, &VerletObject, Entity), With<StaticCollider>>,
+    collision_world: Res<CollisionWorld>,
+    config: Res<PhysicsConfig>,
+) {
+    if (kinematic_query.is_empty()) {
+        return;
+    }
+    let Some(ref root) = collision_world.kd_tree else {
+        return;
+    };
+    let mut bounds = HashMap::new();
+    for (kinematic, collider, verlet_object, entity) in kinematic_query.iter() {
+        bounds.insert(
+            entity,
+            kinematic.swept_bounds(collider, verlet_object, config.sub_steps),
+        );
+    }
+    refit_node(root, &bounds);
+}
+
+//sleeping bodies skip collisions, so moving colliders wake everything in their way
+fn wake_near_kinematic_system(
+    kinematic_query: Query<(&Kinematic, &Collider, &VerletObject), With<StaticCollider>>,
+    mut sleeper_query: Query<(&Collider, &mut VerletObject), Without<StaticCollider>>,
+    config: Res<PhysicsConfig>,
+) {
+    for (kinematic, collider, verlet_object) in kinematic_query.iter() {
+        if (kinematic.step == Vec2::ZERO) {
+            continue;
+        }
+        let bounds = kinematic.swept_bounds(collider, verlet_object, config.sub_steps);
+        for (sleeper_collider, mut sleeper) in sleeper_query.iter_mut() {
+            if (sleeper.sleeping
+                && sleeper_collider
+                    .get_bounding_box(sleeper.position_current)
+                    .intersects(&bounds))
+            {
+                sleeper.wake();
+            }
+        }
+    }
+}
+
+fn refit_node(node: &Arc<Mutex<KDNode>>, bounds: &HashMap<Entity, AABB>) -> AABB {
+    let mut node = node.lock().unwrap();
+    if (!node.objects.is_empty()) {
+        if let Some(bounding_box) = bounds.get(&node.objects[0]) {
+            node.bounding_box = bounding_box.clone();
+        }
+        return node.bounding_box.clone();
+    }
+    let children: Vec<AABB> = [node.left_node.clone(), node.right_node.clone()]
+        .iter()
+        .flatten()
+        .map(|child| refit_node(child, bounds))
+        .collect();
+    if let Some(bounding_box) = children.into_iter().reduce(combine_bounding_boxes) {
+        node.bounding_box = bounding_box;
+    }
+    node.bounding_box.clone()
+}
+
+//solid from above only, everything else passes through from below and the sides
 #[derive(Component)]
+pub struct OneWayPlatform;
+
+fn lands_on_one_way(
+    obj_a: &VerletObject,
+    col_a: &Collider,
+    platform_obj: &VerletObject,
+    platform_col: &Collider,
+    normal: Vec2,
+) -> bool {
+    if (normal.dot(Vec2::Y) < 0.7) {
+        return false;
+    }
+    //only land if the body was above the platform on the previous step
+    let bottom_before = col_a.get_bounding_box(obj_a.position_old).pos.y;
+    let platform_box = platform_col.get_bounding_box(platform_obj.position_current);
+    bottom_before >= platform_box.pos.y + platform_box.size.y - 1.0
+}
+
+//colliders without a material use the friction of the moving VerletObject and don't bounce
+#[derive(Component, Clone, Debug)]
+pub struct PhysicsMaterial {
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+impl PhysicsMaterial {
+    pub fn combine(&self, other: &PhysicsMaterial) -> PhysicsMaterial {
+        PhysicsMaterial {
+            friction: (self.friction * other.friction).sqrt(),
+            restitution: self.restitution.max(other.restitution),
+        }
+    }
+}
+
+//a set of named collision layers, combined with |
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Layer(u32);
+
+impl Layer {
+    pub const NONE: Layer = Layer(0);
+    //static level geometry
+    pub const WORLD: Layer = Layer(1 << 0);
+    //the player and its hand
+    pub const PLAYER: Layer = Layer(1 << 1);
+    //rope segments shot by the player
+    pub const ROPE: Layer = Layer(1 << 2);
+    pub const ALL: Layer = Layer(u32::MAX);
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+    pub fn intersects(self, other: Layer) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl std::ops::BitOr for Layer {
+    type Output = Layer;
+    fn bitor(self, other: Layer) -> Layer {
+        Layer(self.0 | other.0)
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Collider {
     // pub radius: f32,
     pub shape: Shape,
-    pub layer: u32,
-    pub layer_mask: u32,
+    //the layers this collider is on
+    pub layer: Layer,
+    //the layers this collider collides with
+    pub layer_mask: Layer,
     pub trigger: bool,
 }
 
 impl Collider {
-    fn intersect_ray(&self, ray: &Ray, pos: Vec2) -> (bool, f32) {
+    //returns distance along the ray and surface normal, rays starting inside don't hit
+    fn intersect_ray(&self, ray: &Ray, pos: Vec2) -> Option<(f32, Vec2)> {
         match self.shape {
-            Shape::Box { width, height } => return self.get_bounding_box(pos).intersect_ray(ray),
-
-            Shape::Circle { radius } => {
-                return (false, 0.0);
+            Shape::Box { width, height } => {
+                return ray_box_intersection(ray, pos, Vec2::new(width, height))
+            }
+            Shape::Circle { radius } => return ray_circle_intersection(ray, pos, radius),
+            Shape::OrientedBox {
+                width,
+                height,
+                rotation,
+            } => {
+                let local = local_ray(ray, pos, rotation);
+                let (dist, normal) =
+                    ray_box_intersection(&local, Vec2::ZERO, Vec2::new(width, height))?;
+                return Some((dist, Vec2::from_angle(rotation).rotate(normal)));
+            }
+            //a capsule is a line swept by a circle
+            Shape::Capsule {
+                radius,
+                half_length,
+            } => return sweep_circle_box(ray, pos, Vec2::new(0.0, half_length), radius),
+            Shape::ConvexPolygon { ref points } => {
+                return ray_polygon_intersection(ray, pos, points)
+            }
+        }
+    }
+    //moves a circle along the ray, returns travel distance until contact and contact normal
+    fn sweep_circle(&self, ray: &Ray, radius: f32, pos: Vec2) -> Option<(f32, Vec2)> {
+        match self.shape {
+            Shape::Box { width, height } => {
+                return sweep_circle_box(ray, pos, Vec2::new(width, height), radius)
+            }
+            Shape::Circle {
+                radius: other_radius,
+            } => return ray_circle_intersection(ray, pos, radius + other_radius),
+            Shape::OrientedBox {
+                width,
+                height,
+                rotation,
+            } => {
+                let local = local_ray(ray, pos, rotation);
+                let (dist, normal) =
+                    sweep_circle_box(&local, Vec2::ZERO, Vec2::new(width, height), radius)?;
+                return Some((dist, Vec2::from_angle(rotation).rotate(normal)));
+            }
+            Shape::Capsule {
+                radius: other_radius,
+                half_length,
+            } => {
+                return sweep_circle_box(
+                    ray,
+                    pos,
+                    Vec2::new(0.0, half_length),
+                    radius + other_radius,
+                )
+            }
+            Shape::ConvexPolygon { ref points } => {
+                return sweep_circle_polygon(ray, pos, points, radius)
             }
         }
     }
+    pub fn contains_point(&self, pos: Vec2, point: Vec2) -> bool {
+        let (core, radius) = self.shape.core(pos);
+        let (collides, _, _) = sat_collision(&[point], 0.0, &core, radius);
+        collides
+    }
+    //both sides have to include the other's layer in their mask
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.layer_mask.intersects(other.layer) && other.layer_mask.intersects(self.layer)
+    }
     fn get_bounding_box(&self, pos: Vec2) -> AABB {
         match self.shape {
             Shape::Box { width, height } => {
@@ -519,55 +1114,303 @@ impl Collider {
                     size: Vec2::new(radius * 2.0, radius * 2.0),
                 }
             }
+            Shape::OrientedBox {
+                width,
+                height,
+                rotation,
+            } => {
+                let (sin, cos) = rotation.sin_cos();
+                let half_size = Vec2::new(
+                    cos.abs() * width + sin.abs() * height,
+                    sin.abs() * width + cos.abs() * height,
+                );
+                return AABB {
+                    pos: pos - half_size,
+                    size: half_size * 2.0,
+                };
+            }
+            Shape::Capsule {
+                radius,
+                half_length,
+            } => {
+                return AABB {
+                    pos: pos - Vec2::new(radius, half_length + radius),
+                    size: Vec2::new(radius, half_length + radius) * 2.0,
+                }
+            }
+            Shape::ConvexPolygon { ref points } => {
+                let min = points.iter().fold(Vec2::INFINITY, |min, p| min.min(*p));
+                let max = points.iter().fold(Vec2::NEG_INFINITY, |max, p| max.max(*p));
+                return AABB {
+                    pos: pos + min,
+                    size: max - min,
+                };
+            }
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Collision {
     pub normal: Vec2,
+    pub point: Vec2,
+    pub friction: f32,
 }
 #[derive(Component)]
 pub struct TrackCollision {
     pub collisions: HashMap<Entity, Collision>,
-    pub triggers: HashSet<Entity>,
+    pub triggers: HashMap<Entity, Collision>,
     pub last: HashMap<Entity, Collision>,
-    pub last_triggers: HashSet<Entity>,
+    pub last_triggers: HashMap<Entity, Collision>,
 }
 
-#[derive(Component)]
-pub struct ConstantFriction;
+//sent once per fixed tick after the sub steps, entity is the one with the TrackCollision
+#[derive(Event, Clone, Debug)]
+pub struct CollisionStarted {
+    pub entity: Entity,
+    pub other: Entity,
+    pub normal: Vec2,
+    pub point: Vec2,
+}
 
-//returns doesCollide, error-vector, normal-vector
+#[derive(Event, Clone, Debug)]
+pub struct CollisionEnded {
+    pub entity: Entity,
+    pub other: Entity,
+    pub normal: Vec2,
+    pub point: Vec2,
+}
 
-fn circle_circle_collision(pos_a: Vec2, pos_b: Vec2, r_a: f32, r_b: f32) -> (bool, Vec2, Vec2) {
-    let diff = pos_b - pos_a;
-    let dist = diff.length();
-    if (dist > r_a + r_b) {
-        return (false, Vec2::ZERO, Vec2::ZERO);
+#[derive(Event, Clone, Debug)]
+pub struct TriggerEntered {
+    pub entity: Entity,
+    pub trigger: Entity,
+    pub normal: Vec2,
+    pub point: Vec2,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct TriggerExited {
+    pub entity: Entity,
+    pub trigger: Entity,
+    pub normal: Vec2,
+    pub point: Vec2,
+}
+
+fn collision_events_system(
+    collision_query: Query<(&TrackCollision, Entity)>,
+    mut started_events: EventWriter<CollisionStarted>,
+    mut ended_events: EventWriter<CollisionEnded>,
+    mut entered_events: EventWriter<TriggerEntered>,
+    mut exited_events: EventWriter<TriggerExited>,
+) {
+    for (track_collision, entity) in collision_query.iter() {
+        for (other, col) in &track_collision.collisions {
+            if (!track_collision.last.contains_key(other)) {
+                started_events.send(CollisionStarted {
+                    entity,
+                    other: *other,
+                    normal: col.normal,
+                    point: col.point,
+                });
+            }
+        }
+        for (other, col) in &track_collision.last {
+            if (!track_collision.collisions.contains_key(other)) {
+                ended_events.send(CollisionEnded {
+                    entity,
+                    other: *other,
+                    normal: col.normal,
+                    point: col.point,
+                });
+            }
+        }
+        for (trigger, col) in &track_collision.triggers {
+            if (!track_collision.last_triggers.contains_key(trigger)) {
+                entered_events.send(TriggerEntered {
+                    entity,
+                    trigger: *trigger,
+                    normal: col.normal,
+                    point: col.point,
+                });
+            }
+        }
+        for (trigger, col) in &track_collision.last_triggers {
+            if (!track_collision.triggers.contains_key(trigger)) {
+                exited_events.send(TriggerExited {
+                    entity,
+                    trigger: *trigger,
+                    normal: col.normal,
+                    point: col.point,
+                });
+            }
+        }
     }
-    let diff_norm = diff.clone().normalize();
-    let err = diff_norm * (dist - r_a - r_b);
-    return (true, err, -diff_norm);
 }
 
-fn circle_box_collision(
-    pos_a: Vec2,
-    pos_b: Vec2,
-    radius: f32,
-    width: f32,
-    height: f32,
-) -> (bool, Vec2, Vec2) {
-    let left = pos_b.x - width;
-    let right = pos_b.x + width;
-    let closest_x;
-    let axis1;
-    if (pos_a.x - right).abs() < (pos_a.x - left).abs() {
-        closest_x = right;
-        axis1 = Vec2::X;
-    } else {
-        closest_x = left;
-        axis1 = Vec2::NEG_X;
-    };
+#[derive(Component)]
+pub struct ConstantFriction;
+
+//sweeps the object from its old to its current position so it can't pass through thin colliders
+#[derive(Component)]
+pub struct ContinuousCollision;
+
+//how often a swept object can hit something and slide on in one sub step
+const MAX_CCD_SLIDES: usize = 2;
+//gap left to a surface that was hit, so the slide along it doesn't hit it again
+const CCD_SKIN: f32 = 0.01;
+
+fn continuous_collision_system(
+    mut queries: ParamSet<(
+        Query<(&mut VerletObject, &Collider, Entity), With<ContinuousCollision>>,
+        SpatialQuery,
+    )>,
+    one_way_query: Query<&OneWayPlatform>,
+) {
+    let mut sweeps = vec![];
+    for (verlet_object, collider, entity) in queries.p0().iter() {
+        if (verlet_object.fixed) {
+            continue;
+        }
+        //boxes are swept as their inscribed circle
+        let radius = match collider.shape {
+            Shape::Circle { radius } => radius,
+            Shape::Box { width, height } | Shape::OrientedBox { width, height, .. } => {
+                width.min(height)
+            }
+            Shape::Capsule { radius, .. } => radius,
+            Shape::ConvexPolygon { .. } => {
+                collider.get_bounding_box(Vec2::ZERO).size.min_element() / 2.0
+            }
+        };
+        let travel = verlet_object.position_current - verlet_object.position_old;
+        let distance = travel.length();
+        //slow objects are handled by the regular collision pass
+        if (distance <= radius) {
+            continue;
+        }
+        sweeps.push((
+            entity,
+            verlet_object.position_old,
+            radius,
+            travel,
+            collider.layer,
+            collider.layer_mask,
+        ));
+    }
+
+    let mut impacts = vec![];
+    //one-way platforms are left to the regular pass, they only block from one side
+    let solid = |entity: Entity| !one_way_query.contains(entity);
+    let spatial_query = queries.p1();
+    for (entity, origin, radius, travel, layer, layer_mask) in sweeps {
+        let filter = QueryFilter {
+            layer,
+            layer_mask,
+            predicate: Some(&solid),
+            ..QueryFilter::default()
+        };
+        //after a hit the rest of the travel slides along the surface and is swept again
+        let mut position = origin;
+        let mut remaining = travel;
+        let mut normals = vec![];
+        for _ in 0..=MAX_CCD_SLIDES {
+            let distance = remaining.length();
+            if (distance <= f32::EPSILON) {
+                break;
+            }
+            let direction = remaining / distance;
+            let Some(hit) =
+                spatial_query.cast_circle(position, radius, direction, distance, &filter)
+            else {
+                position += remaining;
+                break;
+            };
+            position += direction * hit.distance + hit.normal * CCD_SKIN;
+            let rest = direction * (distance - hit.distance);
+            remaining = rest - hit.normal * rest.dot(hit.normal).min(0.0);
+            normals.push(hit.normal);
+        }
+        if (!normals.is_empty()) {
+            impacts.push((entity, position, normals));
+        }
+    }
+
+    let mut verlet_query = queries.p0();
+    for (entity, position, normals) in impacts {
+        let Ok((mut verlet_object, _, _)) = verlet_query.get_mut(entity) else {
+            continue;
+        };
+        //keep the velocity along the surfaces, the part into them is stopped like a normal contact
+        let mut vel = verlet_object.position_current - verlet_object.position_old;
+        for normal in normals {
+            vel -= normal * vel.dot(normal).min(0.0);
+        }
+        verlet_object.position_current = position;
+        verlet_object.position_old = position - vel;
+    }
+}
+
+//point on the surface of a that touches the other collider
+fn contact_point(obj: &VerletObject, col: &Collider, normal: Vec2) -> Vec2 {
+    match col.shape {
+        Shape::Circle { radius } => obj.position_current - normal * radius,
+        Shape::Box { width, height } => {
+            obj.position_current - normal * (normal.x.abs() * width + normal.y.abs() * height)
+        }
+        Shape::OrientedBox {
+            width,
+            height,
+            rotation,
+        } => {
+            let local_normal = Vec2::from_angle(-rotation).rotate(normal);
+            obj.position_current
+                - normal * (local_normal.x.abs() * width + local_normal.y.abs() * height)
+        }
+        _ => {
+            //deepest point of the core in the direction of the other collider
+            let (core, radius) = col.shape.core(obj.position_current);
+            let support = core
+                .into_iter()
+                .max_by(|a, b| (-normal).dot(*a).partial_cmp(&(-normal).dot(*b)).unwrap())
+                .unwrap_or(obj.position_current);
+            support - normal * radius
+        }
+    }
+}
+
+//returns doesCollide, error-vector, normal-vector
+
+fn circle_circle_collision(pos_a: Vec2, pos_b: Vec2, r_a: f32, r_b: f32) -> (bool, Vec2, Vec2) {
+    let diff = pos_b - pos_a;
+    let dist = diff.length();
+    if (dist > r_a + r_b) {
+        return (false, Vec2::ZERO, Vec2::ZERO);
+    }
+    //coincident centres have no direction, a gets pushed up
+    let diff_norm = diff.try_normalize().unwrap_or(Vec2::NEG_Y);
+    let err = diff_norm * (dist - r_a - r_b);
+    return (true, err, -diff_norm);
+}
+
+fn circle_box_collision(
+    pos_a: Vec2,
+    pos_b: Vec2,
+    radius: f32,
+    width: f32,
+    height: f32,
+) -> (bool, Vec2, Vec2) {
+    let left = pos_b.x - width;
+    let right = pos_b.x + width;
+    let closest_x;
+    let axis1;
+    if (pos_a.x - right).abs() < (pos_a.x - left).abs() {
+        closest_x = right;
+        axis1 = Vec2::X;
+    } else {
+        closest_x = left;
+        axis1 = Vec2::NEG_X;
+    };
 
     let top = pos_b.y + height;
     let bottom = pos_b.y - height;
@@ -582,7 +1425,12 @@ fn circle_box_collision(
     };
     let closest_point = Vec2::new(closest_x, closest_y);
 
-    let check_axis = [(pos_a - closest_point).normalize(), axis1, axis2];
+    //the corner axis is undefined when the centre sits exactly on the corner
+    let check_axis = [
+        (pos_a - closest_point).try_normalize(),
+        Some(axis1),
+        Some(axis2),
+    ];
 
     let mut depth: f32 = f32::INFINITY;
     let mut norm: Vec2 = Vec2::ZERO;
@@ -594,7 +1442,7 @@ fn circle_box_collision(
         Vec2::new(-width, -height),
     ];
 
-    for axis in check_axis {
+    for axis in check_axis.into_iter().flatten() {
         let proj_a = axis.dot(pos_a) - radius;
         let mut proj_b_min = f32::INFINITY;
         let mut proj_b_max = f32::NEG_INFINITY;
@@ -666,14 +1514,106 @@ fn calc_collision(
                 *height,
             );
         }
+        (
+            Shape::Circle { radius },
+            Shape::OrientedBox {
+                width,
+                height,
+                rotation,
+            },
+        ) => {
+            //solve in the frame of the box, where it is axis aligned
+            let inv_rot = Vec2::from_angle(-rotation);
+            let local_pos = inv_rot.rotate(a_obj.position_current - b_obj.position_current);
+            let (collides, err, norm) =
+                circle_box_collision(local_pos, Vec2::ZERO, *radius, *width, *height);
+            let rot = Vec2::from_angle(*rotation);
+            return (collides, rot.rotate(err), rot.rotate(norm));
+        }
+        (Shape::OrientedBox { .. }, Shape::Circle { .. }) => {
+            let (collides, err, norm) = calc_collision(b_obj, a_obj, b_col, a_col);
+            return (collides, -err, -norm);
+        }
         _ => {
+            let (core_a, radius_a) = a_col.shape.core(a_obj.position_current);
+            let (core_b, radius_b) = b_col.shape.core(b_obj.position_current);
+            return sat_collision(&core_a, radius_a, &core_b, radius_b);
+        }
+    }
+}
+
+//separating axis test between two cores grown by their radius, err and normal push a out of b
+fn sat_collision(
+    core_a: &[Vec2],
+    radius_a: f32,
+    core_b: &[Vec2],
+    radius_b: f32,
+) -> (bool, Vec2, Vec2) {
+    let mut axes = vec![];
+    for core in [core_a, core_b] {
+        for i in 0..core.len() {
+            axes.push((core[(i + 1) % core.len()] - core[i]).perp());
+        }
+    }
+    //rounded parts can only be separated along the line to the closest vertex of the other core
+    for (core, radius, other) in [(core_a, radius_a, core_b), (core_b, radius_b, core_a)] {
+        if (radius <= 0.0) {
+            continue;
+        }
+        for point in core {
+            if let Some(closest) = other
+                .iter()
+                .min_by(|a, b| a.distance(*point).partial_cmp(&b.distance(*point)).unwrap())
+            {
+                axes.push(*closest - *point);
+            }
+        }
+    }
+
+    let mut depth = f32::INFINITY;
+    let mut norm = Vec2::ZERO;
+    for axis in axes {
+        let Some(axis) = axis.try_normalize() else {
+            continue;
+        };
+        let (min_a, max_a) = project_polygon(core_a, axis);
+        let (min_b, max_b) = project_polygon(core_b, axis);
+        let (min_a, max_a) = (min_a - radius_a, max_a + radius_a);
+        let (min_b, max_b) = (min_b - radius_b, max_b + radius_b);
+        let push_forward = max_b - min_a;
+        let push_back = max_a - min_b;
+        if (push_forward < 0.0 || push_back < 0.0) {
             return (false, Vec2::ZERO, Vec2::ZERO);
         }
+        if (push_forward < depth) {
+            depth = push_forward;
+            norm = axis;
+        }
+        if (push_back < depth) {
+            depth = push_back;
+            norm = -axis;
+        }
     }
+    return (true, norm * depth, norm);
 }
 
-fn update_verlet_position(mut verlet_query: Query<(&mut VerletObject, &mut Transform)>) {
+fn project_polygon(poly: &[Vec2], axis: Vec2) -> (f32, f32) {
+    poly.iter().map(|vertex| vertex.dot(axis)).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min, max), projection| (min.min(projection), max.max(projection)),
+    )
+}
+
+fn update_verlet_position(
+    mut verlet_query: Query<(&mut VerletObject, &mut Transform)>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    let dt = config.sub_step_dt(&time);
     for (mut verlet_object, mut transform) in verlet_query.iter_mut() {
+        if (verlet_object.sleeping) {
+            continue;
+        }
         if verlet_object.fixed {
             transform.translation = Vec3::new(
                 verlet_object.position_current.x,
@@ -682,12 +1622,7 @@ fn update_verlet_position(mut verlet_query: Query<(&mut VerletObject, &mut Trans
             );
             continue;
         }
-        let vel = (verlet_object.position_current - verlet_object.position_old)
-            * (1.0 - verlet_object.drag);
-        verlet_object.position_old = verlet_object.position_current;
-        verlet_object.position_current =
-            verlet_object.position_old + vel + verlet_object.acceleration;
-        // verlet_object.acceleration = Vec2::ZERO;
+        verlet_object.integrate(dt);
         transform.translation = Vec3::new(
             verlet_object.position_current.x,
             verlet_object.position_current.y,
@@ -696,20 +1631,150 @@ fn update_verlet_position(mut verlet_query: Query<(&mut VerletObject, &mut Trans
     }
 }
 
-fn apply_gravity(mut verlet_query: Query<(&mut VerletObject)>) {
+fn apply_gravity(
+    mut verlet_query: Query<(&mut VerletObject), Without<StaticCollider>>,
+    field_query: Query<(&ForceField, &Collider, &VerletObject), With<StaticCollider>>,
+    config: Res<PhysicsConfig>,
+) {
     for (mut verlet_object) in verlet_query.iter_mut() {
-        verlet_object.accelerate(-Vec2::Y * 0.01);
+        verlet_object.accelerate(config.gravity);
+        if (verlet_object.fixed) {
+            continue;
+        }
+        for (field, collider, field_obj) in field_query.iter() {
+            let pos = verlet_object.position_current;
+            if (!collider.contains_point(field_obj.position_current, pos)) {
+                continue;
+            }
+            let acc = field.acceleration(field_obj.position_current, pos);
+            if (acc != Vec2::ZERO) {
+                verlet_object.accelerate(acc);
+                verlet_object.wake();
+            }
+        }
+    }
+}
+
+//water-like volume, the surface is the top of the collider's bounding box
+#[derive(Component, Clone, Debug)]
+pub struct Fluid {
+    //relative to the bodies, above 1.0 they float
+    pub density: f32,
+    //added to the drag of submerged bodies
+    pub drag: f32,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct Splash {
+    pub entity: Entity,
+    pub fluid: Entity,
+    pub position: Vec2,
+    //in pixels per second, straight into the surface
+    pub speed: f32,
+}
+
+//buoyancy grows with the part of the body below the surface
+fn apply_fluids(
+    mut verlet_query: Query<(&mut VerletObject, Option<&Collider>), Without<StaticCollider>>,
+    fluid_query: Query<(&Fluid, &Collider, &VerletObject), With<StaticCollider>>,
+    config: Res<PhysicsConfig>,
+) {
+    if (fluid_query.is_empty()) {
+        return;
+    }
+    for (mut verlet_object, collider) in verlet_query.iter_mut() {
+        if (verlet_object.fixed) {
+            continue;
+        }
+        let pos = verlet_object.position_current;
+        let (bottom, height) = match collider {
+            Some(collider) => {
+                let bounding_box = collider.get_bounding_box(pos);
+                (bounding_box.pos.y, bounding_box.size.y)
+            }
+            None => (pos.y, 0.0),
+        };
+        for (fluid, fluid_collider, fluid_obj) in fluid_query.iter() {
+            if (!fluid_collider
+                .contains_point(fluid_obj.position_current, Vec2::new(pos.x, bottom)))
+            {
+                continue;
+            }
+            let fluid_box = fluid_collider.get_bounding_box(fluid_obj.position_current);
+            let surface = fluid_box.pos.y + fluid_box.size.y;
+            let submerged = if (height > 0.0) {
+                ((surface - bottom) / height).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            verlet_object.accelerate(-config.gravity * fluid.density * submerged);
+            verlet_object.extra_drag += fluid.drag * submerged;
+            verlet_object.wake();
+        }
+    }
+}
+
+fn splash_system(
+    mut entered_events: EventReader<TriggerEntered>,
+    fluid_query: Query<&Fluid>,
+    verlet_query: Query<&VerletObject>,
+    mut splash_events: EventWriter<Splash>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    let dt = config.sub_step_dt(&time);
+    for entered in entered_events.read() {
+        if (!fluid_query.contains(entered.trigger)) {
+            continue;
+        }
+        let Ok(verlet_object) = verlet_query.get(entered.entity) else {
+            continue;
+        };
+        splash_events.send(Splash {
+            entity: entered.entity,
+            fluid: entered.trigger,
+            position: entered.point,
+            speed: verlet_object.velocity(dt).dot(entered.normal).abs(),
+        });
+    }
+}
+
+//accelerates every body inside the collider of the entity, accelerations are in pixels per second squared
+#[derive(Component, Clone, Debug)]
+pub enum ForceField {
+    Wind { acceleration: Vec2 },
+    //positive strength pulls towards the center, negative pushes away
+    Radial { strength: f32 },
+    //positive strength spins counter-clockwise around the center
+    Vortex { strength: f32 },
+}
+
+impl ForceField {
+    fn acceleration(&self, center: Vec2, pos: Vec2) -> Vec2 {
+        let to_center = (center - pos).normalize_or_zero();
+        match *self {
+            ForceField::Wind { acceleration } => acceleration,
+            ForceField::Radial { strength } => to_center * strength,
+            ForceField::Vortex { strength } => -to_center.perp() * strength,
+        }
     }
 }
 
 fn reset_forces(mut verlet_query: Query<(&mut VerletObject)>) {
     for (mut verlet_object) in verlet_query.iter_mut() {
         verlet_object.acceleration = Vec2::ZERO;
+        verlet_object.extra_drag = 0.0;
     }
 }
 
-fn reset_collisions(mut collision_query: Query<(&mut TrackCollision)>) {
-    for (mut col) in collision_query.iter_mut() {
+fn reset_collisions(mut collision_query: Query<(&mut TrackCollision, Option<&VerletObject>)>) {
+    for (mut col, verlet_object) in collision_query.iter_mut() {
+        //sleeping bodies keep their contacts without reporting them again
+        if (verlet_object.is_some_and(|verlet_object| verlet_object.sleeping)) {
+            col.last = col.collisions.clone();
+            col.last_triggers = col.triggers.clone();
+            continue;
+        }
         col.last = std::mem::take(&mut col.collisions);
         col.last_triggers = std::mem::take(&mut col.triggers);
         // col.collisions.clear();
@@ -723,7 +1788,8 @@ fn apply_constraints(mut verlet_query: Query<&mut VerletObject>) {
         if (verlet_object.position_current.y < -800.0) {
             let normal = Vec2::Y;
 
-            apply_friction(normal, &mut verlet_object);
+            let friction = verlet_object.friction;
+            apply_friction(normal, friction, REFERENCE_SUB_STEP_DT, &mut verlet_object);
             verlet_object.position_current.y = -800.0;
         }
         // let dirr = verlet_object.position_current - origin;
@@ -743,36 +1809,161 @@ fn constant_friction_system(
         ),
         With<ConstantFriction>,
     >,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
 ) {
+    let dt = config.sub_step_dt(&time);
     for (mut verlet_object, _collider, track_collision, mut transform) in verlet_query.iter_mut() {
         //using last collisions to avoid inconsistent friction due to jitter
         for col in &track_collision.last {
-            apply_friction(col.1.normal, &mut verlet_object);
+            apply_friction(col.1.normal, col.1.friction, dt, &mut verlet_object);
         }
     }
 }
 
-fn apply_friction(normal: Vec2, verlet_object: &mut VerletObject) -> Vec2 {
+//friction and cushioning were tuned as fractions per sub step at 64hz with 8 sub steps
+const REFERENCE_SUB_STEP_DT: f32 = 1.0 / 512.0;
+
+//rescales a fraction removed per reference sub step to one removed over dt,
+//so the same share of velocity is lost per second at any rate
+fn fraction_for_dt(fraction: f32, dt: f32) -> f32 {
+    if (fraction >= 1.0) {
+        return 1.0;
+    }
+    let rate = -(1.0 - fraction.max(0.0)).ln() / REFERENCE_SUB_STEP_DT;
+    1.0 - (-rate * dt).exp()
+}
+
+fn apply_friction(normal: Vec2, friction: f32, dt: f32, verlet_object: &mut VerletObject) -> Vec2 {
     let vel = verlet_object.position_current - verlet_object.position_old;
     let vel_n = normal * normal.dot(vel);
     let vel_t = vel - vel_n;
-    verlet_object.position_current -= vel_t * verlet_object.friction;
+    verlet_object.position_current -= vel_t * fraction_for_dt(friction, dt);
     return vel_t;
 }
 
-fn stick_constraints(stick_query: Query<(&Stick)>, mut verlet_query: Query<&mut VerletObject>) {
-    for (mut stick) in stick_query.iter() {
-        if let Ok([mut obj1, mut obj2]) = verlet_query.get_many_mut([stick.ent1, stick.ent2]) {
-            let diff = obj2.position_current - obj1.position_current;
-            let err = diff.length() - stick.length;
+//vel is the velocity before the collision was resolved
+fn apply_restitution(normal: Vec2, vel: Vec2, restitution: f32, verlet_object: &mut VerletObject) {
+    let vel_dot = normal.dot(vel);
+    if (restitution <= 0.0 || vel_dot >= 0.0) {
+        return;
+    }
+    let bounced = vel - normal * vel_dot * (1.0 + restitution);
+    verlet_object.position_old = verlet_object.position_current - bounced;
+}
 
-            let ma = if obj1.fixed { 0.0 } else { 1.0 };
-            let mb = if obj2.fixed { 0.0 } else { 1.0 };
-            if (ma + mb <= 0.0) {
+fn stick_constraints(
+    stick_query: Query<(&Stick)>,
+    mut verlet_query: Query<&mut VerletObject>,
+    config: Res<PhysicsConfig>,
+) {
+    for _ in 0..config.stick_iterations {
+        for (mut stick) in stick_query.iter() {
+            if let Ok([mut obj1, mut obj2]) = verlet_query.get_many_mut([stick.ent1, stick.ent2]) {
+                if (obj1.sleeping && obj2.sleeping) {
+                    continue;
+                }
+                //an awake body wakes the rest of its island through the sticks
+                if (obj1.sleeping) {
+                    obj1.wake();
+                }
+                if (obj2.sleeping) {
+                    obj2.wake();
+                }
+                solve_stick(&mut obj1, &mut obj2, stick.length);
+            }
+        }
+    }
+}
+
+//bodies connected by sticks form an island, which only falls asleep as a whole
+fn sleep_system(
+    mut verlet_query: Query<(&mut VerletObject, Entity)>,
+    stick_query: Query<&Stick>,
+    time: Res<Time<Fixed>>,
+    config: Res<PhysicsConfig>,
+) {
+    let dt = config.sub_step_dt(&time);
+    let mut parents: HashMap<Entity, Entity> = HashMap::new();
+    for (mut verlet_object, entity) in verlet_query.iter_mut() {
+        if (verlet_object.fixed || verlet_object.sleeping) {
+            continue;
+        }
+        if (verlet_object.velocity(dt).length() < config.sleep_speed) {
+            verlet_object.still_ticks += 1;
+        } else {
+            verlet_object.still_ticks = 0;
+        }
+        parents.insert(entity, entity);
+    }
+    for stick in stick_query.iter() {
+        if let (Some(root1), Some(root2)) = (
+            island_root(&parents, stick.ent1),
+            island_root(&parents, stick.ent2),
+        ) {
+            parents.insert(root1, root2);
+        }
+    }
+
+    let mut restless = HashSet::new();
+    for (verlet_object, entity) in verlet_query.iter() {
+        if let Some(root) = island_root(&parents, entity) {
+            if (verlet_object.still_ticks < config.sleep_ticks) {
+                restless.insert(root);
+            }
+        }
+    }
+    for (mut verlet_object, entity) in verlet_query.iter_mut() {
+        if let Some(root) = island_root(&parents, entity) {
+            if (!restless.contains(&root)) {
+                verlet_object.sleeping = true;
+                verlet_object.position_old = verlet_object.position_current;
+            }
+        }
+    }
+}
+
+fn island_root(parents: &HashMap<Entity, Entity>, mut entity: Entity) -> Option<Entity> {
+    let mut parent = *parents.get(&entity)?;
+    while (parent != entity) {
+        entity = parent;
+        parent = parents[&entity];
+    }
+    Some(entity)
+}
+
+fn solve_stick(obj1: &mut VerletObject, obj2: &mut VerletObject, length: f32) {
+    let diff = obj2.position_current - obj1.position_current;
+    let err = diff.length() - length;
+
+    let ma = obj1.effective_inverse_mass();
+    let mb = obj2.effective_inverse_mass();
+    if (ma + mb <= 0.0) {
+        return;
+    }
+    obj1.position_current += diff.normalize() * err * (ma / (ma + mb));
+    obj2.position_current -= diff.normalize() * err * (mb / (ma + mb));
+}
+
+fn break_sticks_system(
+    mut commands: Commands,
+    stick_query: Query<(&Stick, &BreakableStick, Entity)>,
+    verlet_query: Query<&VerletObject>,
+    mut snapped_events: EventWriter<RopeSnapped>,
+) {
+    for (stick, breakable, entity) in stick_query.iter() {
+        if let Ok([obj1, obj2]) = verlet_query.get_many([stick.ent1, stick.ent2]) {
+            let length = (obj2.position_current - obj1.position_current).length();
+            if (length <= stick.length * (1.0 + breakable.max_stretch)) {
                 continue;
             }
-            obj1.position_current += diff.normalize() * err * (ma / (ma + mb));
-            obj2.position_current -= diff.normalize() * err * (mb / (ma + mb));
+            commands.entity(entity).despawn();
+            snapped_events.send(RopeSnapped {
+                stick: entity,
+                ent1: stick.ent1,
+                ent2: stick.ent2,
+                position: (obj1.position_current + obj2.position_current) / 2.0,
+            });
         }
     }
 }
@@ -780,7 +1971,10 @@ fn stick_constraints(stick_query: Query<(&Stick)>, mut verlet_query: Query<&mut
 fn cushion_system(
     mut rope_holder_query: Query<(&mut RopeHolder, &mut VerletObject)>,
     collider_tacker_query: Query<&TrackCollision>,
+    config: Res<PhysicsConfig>,
+    time: Res<Time<Fixed>>,
 ) {
+    let cushion = fraction_for_dt(config.cushion_factor, config.sub_step_dt(&time));
     for (mut rope_holder, mut verlet_object) in rope_holder_query.iter_mut() {
         let hand = rope_holder.hand;
         if let Ok(track_col) = collider_tacker_query.get(hand) {
@@ -789,7 +1983,7 @@ fn cushion_system(
                 let mut vel = verlet_object.position_current - verlet_object.position_old;
                 let vel_dot = norm.dot(vel);
                 if (vel_dot < 0.0) {
-                    vel -= (norm * vel_dot) * 0.2;
+                    vel -= (norm * vel_dot) * cushion;
                     verlet_object.position_old = verlet_object.position_current - vel;
                 }
             }
@@ -851,24 +2045,15 @@ pub struct Position {
     pub pos: Vec2,
 }
 
+//the target comes from the mouse entity's Position, so this also runs without a window or camera
 fn mouse_constraint_system(
-    camera_query: Single<(&Camera, &GlobalTransform)>,
-    windows: Query<&Window>,
     mut player_query: Query<(&mut RopeHolder, Entity)>,
     mut verlet_object_query: Query<&mut VerletObject>,
     mut pos_query: Query<&Position>,
+    config: Res<PhysicsConfig>,
+    time: Res<Time<Fixed>>,
 ) {
-    let (camera, camera_transform) = *camera_query;
-
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-
-    let cursor_position = window.cursor_position();
-
-    // Calculate a world position based on the cursor's position.
-    let point =
-        cursor_position.and_then(|pos| camera.viewport_to_world_2d(camera_transform, pos).ok());
+    let dt = config.sub_step_dt(&time);
 
     for (mut rope_holer, entity) in player_query.iter_mut() {
         if let Ok([mut obj1, mut obj2]) =
@@ -882,7 +2067,7 @@ fn mouse_constraint_system(
                 if (diff_obj2.length() == 0.0) {
                     continue;
                 }
-                let length = diff_obj2.length().min(64.0);
+                let length = diff_obj2.length().min(config.hand_leash_length);
                 let ideal_pos = obj1.position_current + diff_obj2.normalize() * length;
                 // let ideal_pos = obj1.position_current - Vec2::Y * 50.0;
                 let diff = ideal_pos - obj2.position_current;
@@ -891,18 +2076,27 @@ fn mouse_constraint_system(
                 }
                 let diff_norm = diff.clone().normalize();
 
-                obj2.position_current +=
-                    diff_norm * (diff.length() / 8.0).min(rope_holer.power) * 0.95;
+                let m1 = obj1.effective_inverse_mass();
+                let m2 = obj2.effective_inverse_mass();
+                if (m1 + m2 <= 0.0) {
+                    continue;
+                }
+                let w1 = m1 / (m1 + m2);
+                let w2 = m2 / (m1 + m2);
+
+                let step = (diff.length() / config.sub_steps as f32).min(rope_holer.power * dt);
+                obj1.wake();
+                obj2.wake();
+                obj2.position_current += diff_norm * step * w2;
 
-                obj1.position_current -=
-                    diff_norm * (diff.length() / 8.0).min(rope_holer.power) * 0.05;
+                obj1.position_current -= diff_norm * step * w1;
 
                 let hand_diff = obj2.position_current - obj1.position_current;
                 let hand_diff_norm = hand_diff.clone().normalize();
-                let err = 64.0 - hand_diff.length();
+                let err = config.hand_leash_length - hand_diff.length();
                 if (err < 0.0) {
-                    obj1.position_current -= hand_diff_norm * err * 0.05;
-                    obj2.position_current += hand_diff_norm * err * 0.95;
+                    obj1.position_current -= hand_diff_norm * err * w1;
+                    obj2.position_current += hand_diff_norm * err * w2;
                 }
 
                 if (obj1.position_current.is_nan()) {
@@ -914,44 +2108,1084 @@ fn mouse_constraint_system(
     }
 }
 
-pub fn raycast(
-    ray: &Ray,
-    collider_query: &Query<(&Collider, &VerletObject)>,
-    collision_world: &Res<CollisionWorld>,
-) -> Option<(f32, Entity)> {
-    let mut objects: Vec<(Entity, f32)> = vec![];
-    find_ray_collision_entities(ray, &collision_world.kd_tree, &mut objects);
-    if objects.len() == 0 {
+fn ray_box_intersection(ray: &Ray, center: Vec2, half_size: Vec2) -> Option<(f32, Vec2)> {
+    if (!ray.origin.is_finite()
+        || !ray.direction.is_finite()
+        || ray.direction.length_squared() == 0.0)
+    {
         return None;
     }
+    let min = center - half_size;
+    let max = center + half_size;
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+    for axis in 0..2 {
+        let origin = ray.origin[axis];
+        let direction = ray.direction[axis];
+        //parallel to the slab, 0 * inf would turn the bounds into NaN
+        if (direction == 0.0) {
+            if (origin < min[axis] || origin > max[axis]) {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin) / direction;
+        let t2 = (max[axis] - origin) / direction;
+        if (t1.min(t2) >= t_enter) {
+            t_enter = t1.min(t2);
+            normal = Vec2::ZERO;
+            normal[axis] = -direction.signum();
+        }
+        t_exit = t_exit.min(t1.max(t2));
+    }
+    if (t_enter < 0.0 || t_enter > t_exit) {
+        return None;
+    }
+    Some((t_enter, normal))
+}
 
-    objects.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+fn ray_circle_intersection(ray: &Ray, center: Vec2, radius: f32) -> Option<(f32, Vec2)> {
+    let to_origin = ray.origin - center;
+    let b = to_origin.dot(ray.direction);
+    let c = to_origin.length_squared() - radius * radius;
+    if (c < 0.0) {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if (b > 0.0 || discriminant < 0.0) {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    let normal = (ray.origin + ray.direction * t - center) / radius;
+    Some((t, normal))
+}
 
-    let mut curr_ent: Option<Entity> = None;
-    let mut cur_dist = f32::INFINITY;
+//the box grown by the radius has rounded corners, so test both stretched boxes and the corners
+fn sweep_circle_box(ray: &Ray, center: Vec2, half_size: Vec2, radius: f32) -> Option<(f32, Vec2)> {
+    let closest = ray.origin.clamp(center - half_size, center + half_size);
+    if (ray.origin.distance(closest) < radius) {
+        return None;
+    }
+    let mut hits = vec![
+        ray_box_intersection(ray, center, half_size + Vec2::new(radius, 0.0)),
+        ray_box_intersection(ray, center, half_size + Vec2::new(0.0, radius)),
+    ];
+    for corner in [
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+        Vec2::new(half_size.x, -half_size.y),
+        Vec2::new(-half_size.x, -half_size.y),
+    ] {
+        hits.push(ray_circle_intersection(ray, center + corner, radius));
+    }
+    hits.into_iter()
+        .flatten()
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+}
 
-    for i in 0..objects.len() {
-        let (ent, dist) = objects[i];
-        if dist > cur_dist {
-            break;
+//clips the ray against every edge, points are relative to pos and counter-clockwise
+fn ray_polygon_intersection(ray: &Ray, pos: Vec2, points: &[Vec2]) -> Option<(f32, Vec2)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+    for i in 0..points.len() {
+        let start = pos + points[i];
+        let Some(edge_normal) = (points[(i + 1) % points.len()] - points[i])
+            .perp()
+            .try_normalize()
+            .map(|n| -n)
+        else {
+            continue;
+        };
+        let dist = edge_normal.dot(ray.origin - start);
+        let speed = edge_normal.dot(ray.direction);
+        if (speed == 0.0) {
+            if (dist > 0.0) {
+                return None;
+            }
+            continue;
         }
-        if let Ok((collider, verlet_obj)) = collider_query.get(ent) {
-            if (collider.trigger) {
-                continue;
+        let t = -dist / speed;
+        if (speed < 0.0) {
+            if (t > t_enter) {
+                t_enter = t;
+                normal = edge_normal;
             }
-            let (hit, hit_dist) = collider.intersect_ray(ray, verlet_obj.position_current);
-            if (!hit) {
+        } else {
+            t_exit = t_exit.min(t);
+        }
+    }
+    if (t_enter < 0.0 || t_enter > t_exit) {
+        return None;
+    }
+    Some((t_enter, normal))
+}
+
+//like sweep_circle_box, the polygon grown by the radius is its edges pushed out plus the corners
+fn sweep_circle_polygon(ray: &Ray, pos: Vec2, points: &[Vec2], radius: f32) -> Option<(f32, Vec2)> {
+    let world: Vec<Vec2> = points.iter().map(|p| pos + *p).collect();
+    let (collides, _, _) = sat_collision(&[ray.origin], radius, &world, 0.0);
+    if (collides) {
+        return None;
+    }
+    let mut hits = vec![];
+    for i in 0..world.len() {
+        let start = world[i];
+        let end = world[(i + 1) % world.len()];
+        hits.push(ray_circle_intersection(ray, start, radius));
+        let Some(edge_normal) = (end - start).perp().try_normalize().map(|n| -n) else {
+            continue;
+        };
+        if (edge_normal.dot(ray.direction) >= 0.0) {
+            continue;
+        }
+        let offset = edge_normal * radius;
+        let (hit, t) = line_line_intersection(
+            ray.origin,
+            ray.origin + ray.direction,
+            start + offset,
+            end + offset,
+            true,
+        );
+        if (hit) {
+            hits.push(Some((t, edge_normal)));
+        }
+    }
+    hits.into_iter()
+        .flatten()
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+}
+
+//for shape casts point is the contact point, the shape itself stops at origin + direction * distance
+pub struct RayHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+pub struct QueryFilter<'a> {
+    //only colliders whose layer is in the mask are considered
+    pub layer_mask: Layer,
+    //colliders whose own mask excludes this layer are skipped too
+    pub layer: Layer,
+    pub include_triggers: bool,
+    pub max_distance: f32,
+    pub predicate: Option<&'a dyn Fn(Entity) -> bool>,
+}
+
+impl Default for QueryFilter<'_> {
+    fn default() -> Self {
+        QueryFilter {
+            layer_mask: Layer::ALL,
+            layer: Layer::ALL,
+            include_triggers: false,
+            max_distance: f32::INFINITY,
+            predicate: None,
+        }
+    }
+}
+
+impl QueryFilter<'_> {
+    fn accepts(&self, entity: Entity, collider: &Collider) -> bool {
+        if (collider.trigger && !self.include_triggers) {
+            return false;
+        }
+        if (!self.layer_mask.intersects(collider.layer)
+            || !collider.layer_mask.intersects(self.layer))
+        {
+            return false;
+        }
+        if let Some(predicate) = self.predicate {
+            return predicate(entity);
+        }
+        true
+    }
+}
+
+//queries against the static colliders in the CollisionWorld
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's> {
+    collision_world: Res<'w, CollisionWorld>,
+    collider_query: Query<'w, 's, (&'static Collider, &'static VerletObject)>,
+    ray_log: Option<Res<'w, RayLog>>,
+}
+
+//while this resource exists every ray and circle cast is recorded, e.g. for the debug view
+#[derive(Resource, Default)]
+pub struct RayLog {
+    pub rays: Mutex<Vec<LoggedRay>>,
+}
+
+pub struct LoggedRay {
+    pub origin: Vec2,
+    pub end: Vec2,
+    pub radius: f32,
+    pub hit: Option<Vec2>,
+}
+
+const MAX_LOGGED_RAYS: usize = 256;
+
+impl SpatialQuery<'_, '_> {
+    pub fn cast_ray(&self, ray: &Ray, filter: &QueryFilter) -> Option<RayHit> {
+        let mut objects: Vec<(Entity, f32)> = vec![];
+        find_ray_collision_entities(ray, &self.collision_world.kd_tree, &mut objects);
+        objects.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let mut closest: Option<RayHit> = None;
+        for (ent, dist) in objects {
+            let cur_dist = closest
+                .as_ref()
+                .map_or(filter.max_distance, |hit| hit.distance);
+            if (dist > cur_dist) {
+                break;
+            }
+            if let Some(hit) = self.intersect(ent, ray, filter) {
+                if (hit.distance <= cur_dist) {
+                    closest = Some(hit);
+                }
+            }
+        }
+        self.log_ray(
+            ray.origin,
+            ray.direction,
+            filter.max_distance,
+            0.0,
+            &closest,
+        );
+        closest
+    }
+
+    fn log_ray(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        radius: f32,
+        hit: &Option<RayHit>,
+    ) {
+        let Some(ref ray_log) = self.ray_log else {
+            return;
+        };
+        let Ok(mut rays) = ray_log.rays.lock() else {
+            return;
+        };
+        if (rays.len() >= MAX_LOGGED_RAYS) {
+            return;
+        }
+        //misses without a max distance are drawn a screen wide
+        let distance = hit
+            .as_ref()
+            .map_or(max_distance.min(1000.0), |hit| hit.distance);
+        rays.push(LoggedRay {
+            origin,
+            end: origin + direction * distance,
+            radius,
+            hit: hit.as_ref().map(|hit| hit.point),
+        });
+    }
+
+    //all hits along the ray, sorted by distance
+    pub fn cast_ray_all(&self, ray: &Ray, filter: &QueryFilter) -> Vec<RayHit> {
+        let mut objects: Vec<(Entity, f32)> = vec![];
+        find_ray_collision_entities(ray, &self.collision_world.kd_tree, &mut objects);
+
+        let mut hits: Vec<RayHit> = objects
+            .iter()
+            .filter_map(|(ent, _)| self.intersect(*ent, ray, filter))
+            .filter(|hit| hit.distance <= filter.max_distance)
+            .collect();
+        hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        hits
+    }
+
+    pub fn cast_circle(
+        &self,
+        origin: Vec2,
+        radius: f32,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RayHit> {
+        let max_distance = max_distance.min(filter.max_distance);
+        let end = origin + direction * max_distance;
+        let sweep_box = combine_bounding_boxes(
+            AABB {
+                pos: origin - Vec2::splat(radius),
+                size: Vec2::splat(radius * 2.0),
+            },
+            AABB {
+                pos: end - Vec2::splat(radius),
+                size: Vec2::splat(radius * 2.0),
+            },
+        );
+        let mut colliders = vec![];
+        find_collision_entities(&sweep_box, &self.collision_world.kd_tree, &mut colliders);
+
+        let ray = Ray { origin, direction };
+        let mut closest: Option<RayHit> = None;
+        for ent in colliders {
+            let Ok((collider, verlet_obj)) = self.collider_query.get(ent) else {
+                continue;
+            };
+            if (!filter.accepts(ent, collider)) {
                 continue;
             }
-            if (hit_dist > cur_dist) {
+            let Some((distance, normal)) =
+                collider.sweep_circle(&ray, radius, verlet_obj.position_current)
+            else {
+                continue;
+            };
+            if (distance > max_distance
+                || closest.as_ref().is_some_and(|hit| hit.distance <= distance))
+            {
                 continue;
             }
-            cur_dist = hit_dist;
-            curr_ent = Some(ent);
+            closest = Some(RayHit {
+                entity: ent,
+                distance,
+                point: origin + direction * distance - normal * radius,
+                normal,
+            });
         }
+        self.log_ray(origin, direction, max_distance, radius, &closest);
+        closest
     }
-    if let Some(ent) = curr_ent {
-        return Some((cur_dist, ent));
+
+    pub fn overlap_aabb(&self, bounding_box: &AABB, filter: &QueryFilter) -> Vec<Entity> {
+        let mut colliders = vec![];
+        find_collision_entities(bounding_box, &self.collision_world.kd_tree, &mut colliders);
+        colliders
+            .into_iter()
+            .filter(|ent| {
+                self.collider_query
+                    .get(*ent)
+                    .is_ok_and(|(collider, _)| filter.accepts(*ent, collider))
+            })
+            .collect()
+    }
+
+    pub fn overlap_circle(&self, center: Vec2, radius: f32, filter: &QueryFilter) -> Vec<Entity> {
+        let circle = Collider {
+            shape: Shape::Circle { radius },
+            layer: Layer::NONE,
+            layer_mask: Layer::NONE,
+            trigger: false,
+        };
+        let circle_obj = VerletObject {
+            position_current: center,
+            position_old: center,
+            ..Default::default()
+        };
+        self.overlap_aabb(&circle.get_bounding_box(center), filter)
+            .into_iter()
+            .filter(|ent| {
+                let (collider, verlet_obj) = self.collider_query.get(*ent).unwrap();
+                calc_collision(&circle_obj, verlet_obj, &circle, collider).0
+            })
+            .collect()
+    }
+
+    fn intersect(&self, entity: Entity, ray: &Ray, filter: &QueryFilter) -> Option<RayHit> {
+        let (collider, verlet_obj) = self.collider_query.get(entity).ok()?;
+        if (!filter.accepts(entity, collider)) {
+            return None;
+        }
+        let (distance, normal) = collider.intersect_ray(ray, verlet_obj.position_current)?;
+        Some(RayHit {
+            entity,
+            distance,
+            point: ray.origin + ray.direction * distance,
+            normal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    //mirrors a fixed tick: gravity is applied once, then every sub step integrates and solves sticks
+    fn simulate(
+        rate: f32,
+        seconds: f32,
+        objects: &mut Vec<VerletObject>,
+        sticks: &[(usize, usize, f32)],
+    ) {
+        let config = PhysicsConfig::default();
+        let dt = 1.0 / rate / config.sub_steps as f32;
+        let ticks = (seconds * rate).round() as usize;
+        for _ in 0..ticks {
+            for obj in objects.iter_mut() {
+                obj.acceleration = config.gravity;
+            }
+            for _ in 0..config.sub_steps {
+                for obj in objects.iter_mut() {
+                    if !obj.fixed {
+                        obj.integrate(dt);
+                    }
+                }
+                for &(a, b, length) in sticks {
+                    let (first, second) = objects.split_at_mut(b);
+                    solve_stick(&mut first[a], &mut second[0], length);
+                }
+            }
+        }
+    }
+
+    fn body(pos: Vec2, drag: f32) -> VerletObject {
+        VerletObject {
+            position_current: pos,
+            position_old: pos,
+            drag,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn free_fall_matches_analytic_solution() {
+        for rate in [64.0, 120.0] {
+            let mut objects = vec![body(Vec2::ZERO, 0.0)];
+            simulate(rate, 0.5, &mut objects, &[]);
+            let expected = 0.5 * PhysicsConfig::default().gravity.y * 0.25;
+            let y = objects[0].position_current.y;
+            assert!(
+                (y - expected).abs() < expected.abs() * 0.01,
+                "{rate} Hz: {y} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn projectile_with_drag_is_rate_independent() {
+        let mut results = vec![];
+        for rate in [64.0, 120.0] {
+            let config = PhysicsConfig::default();
+            let mut objects = vec![body(Vec2::ZERO, 0.512)];
+            objects[0].set_velocity(
+                Vec2::new(300.0, 900.0),
+                1.0 / rate / config.sub_steps as f32,
+            );
+            simulate(rate, 0.5, &mut objects, &[]);
+            results.push(objects[0].position_current);
+        }
+        assert!(
+            results[0].distance(results[1]) < 2.0,
+            "{:?} vs {:?}",
+            results[0],
+            results[1]
+        );
+    }
+
+    #[test]
+    fn stick_moves_lighter_body_more() {
+        let mut heavy = body(Vec2::ZERO, 0.0);
+        heavy.inverse_mass = 0.25;
+        let mut light = body(Vec2::new(20.0, 0.0), 0.0);
+        solve_stick(&mut heavy, &mut light, 10.0);
+        assert!((light.position_current.distance(heavy.position_current) - 10.0).abs() < 1e-4);
+        assert!((heavy.position_current.x - 2.0).abs() < 1e-4);
+        assert!((light.position_current.x - 12.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn one_way_platform_only_catches_from_above() {
+        let circle = Collider {
+            shape: Shape::Circle { radius: 4.0 },
+            layer: Layer::PLAYER,
+            layer_mask: Layer::WORLD,
+            trigger: false,
+        };
+        let platform_col = Collider {
+            shape: Shape::Box {
+                width: 20.0,
+                height: 5.0,
+            },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        let platform = body(Vec2::ZERO, 0.0);
+
+        let mut falling = body(Vec2::new(0.0, 8.0), 0.0);
+        falling.position_old = Vec2::new(0.0, 10.0);
+        assert!(lands_on_one_way(
+            &falling,
+            &circle,
+            &platform,
+            &platform_col,
+            Vec2::Y
+        ));
+
+        let mut jumping = body(Vec2::new(0.0, 4.0), 0.0);
+        jumping.position_old = Vec2::new(0.0, 0.0);
+        assert!(!lands_on_one_way(
+            &jumping,
+            &circle,
+            &platform,
+            &platform_col,
+            Vec2::Y
+        ));
+        assert!(!lands_on_one_way(
+            &falling,
+            &circle,
+            &platform,
+            &platform_col,
+            Vec2::X
+        ));
+    }
+
+    #[test]
+    fn ray_reports_hit_point_normal_and_skips_shapes_it_starts_in() {
+        let ray = Ray {
+            origin: Vec2::new(-50.0, 0.0),
+            direction: Vec2::X,
+        };
+        let (dist, normal) = ray_box_intersection(&ray, Vec2::ZERO, Vec2::new(10.0, 5.0)).unwrap();
+        assert!((dist - 40.0).abs() < 1e-4);
+        assert_eq!(normal, Vec2::NEG_X);
+
+        let (dist, normal) = ray_circle_intersection(&ray, Vec2::ZERO, 10.0).unwrap();
+        assert!((dist - 40.0).abs() < 1e-4);
+        assert!(normal.distance(Vec2::NEG_X) < 1e-4);
+
+        let inside = Ray {
+            origin: Vec2::ZERO,
+            direction: Vec2::Y,
+        };
+        assert!(ray_box_intersection(&inside, Vec2::ZERO, Vec2::new(10.0, 5.0)).is_none());
+        assert!(ray_circle_intersection(&inside, Vec2::ZERO, 10.0).is_none());
+    }
+
+    #[test]
+    fn swept_circle_stops_on_faces_and_rounds_corners() {
+        let half_size = Vec2::new(10.0, 5.0);
+        let ray = Ray {
+            origin: Vec2::new(-50.0, 0.0),
+            direction: Vec2::X,
+        };
+        let (dist, normal) = sweep_circle_box(&ray, Vec2::ZERO, half_size, 4.0).unwrap();
+        assert!((dist - 36.0).abs() < 1e-4);
+        assert_eq!(normal, Vec2::NEG_X);
+
+        //passes above the top face but clips the corner
+        let grazing = Ray {
+            origin: Vec2::new(-50.0, 8.0),
+            direction: Vec2::X,
+        };
+        let (dist, normal) = sweep_circle_box(&grazing, Vec2::ZERO, half_size, 4.0).unwrap();
+        let center = grazing.origin + grazing.direction * dist;
+        assert!((center.distance(Vec2::new(-10.0, 5.0)) - 4.0).abs() < 1e-3);
+        assert!(normal.x < 0.0 && normal.y > 0.0);
+
+        let miss = Ray {
+            origin: Vec2::new(-50.0, 9.5),
+            direction: Vec2::X,
+        };
+        assert!(sweep_circle_box(&miss, Vec2::ZERO, half_size, 4.0).is_none());
+    }
+
+    #[test]
+    fn kinematic_paths_loop() {
+        let linear = KinematicPath::Linear {
+            from: Vec2::ZERO,
+            to: Vec2::new(100.0, 0.0),
+            period: 2.0,
+        };
+        assert!(linear.position_at(1.0).distance(Vec2::new(100.0, 0.0)) < 1e-3);
+        assert!(linear.position_at(2.5).distance(Vec2::new(50.0, 0.0)) < 1e-3);
+        for period in [0.0, -1.0, f32::NAN] {
+            let broken = KinematicPath::Linear {
+                from: Vec2::ZERO,
+                to: Vec2::new(100.0, 0.0),
+                period,
+            };
+            assert_eq!(broken.position_at(1.0), Vec2::ZERO);
+        }
+
+        let waypoints = KinematicPath::Waypoints {
+            points: vec![Vec2::ZERO, Vec2::new(100.0, 0.0), Vec2::new(100.0, 100.0)],
+            speed: 100.0,
+        };
+        assert!(waypoints.position_at(1.5).distance(Vec2::new(100.0, 50.0)) < 1e-3);
+        //the way back from the last point to the first is part of the loop
+        let total = 2.0 + 2.0_f32.sqrt();
+        assert!(waypoints.position_at(total).distance(Vec2::ZERO) < 1e-3);
+    }
+
+    #[test]
+    fn rotated_box_collides_along_its_own_axes() {
+        let diamond = Collider {
+            shape: Shape::OrientedBox {
+                width: 10.0,
+                height: 10.0,
+                rotation: std::f32::consts::FRAC_PI_4,
+            },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        let ball = Collider {
+            shape: Shape::Circle { radius: 2.0 },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        //the top corner of the diamond is at y = 10 * sqrt(2)
+        let above = body(Vec2::new(0.0, 15.0), 0.0);
+        let (collides, err, normal) =
+            calc_collision(&above, &body(Vec2::ZERO, 0.0), &ball, &diamond);
+        assert!(collides);
+        assert!(normal.y > 0.0 && err.length() > 0.0);
+        let beside_corner = body(Vec2::new(9.0, 9.0), 0.0);
+        let (collides, _, _) =
+            calc_collision(&beside_corner, &body(Vec2::ZERO, 0.0), &ball, &diamond);
+        assert!(!collides);
+
+        let ray = Ray {
+            origin: Vec2::new(-50.0, 0.0),
+            direction: Vec2::X,
+        };
+        let (dist, normal) = diamond.intersect_ray(&ray, Vec2::ZERO).unwrap();
+        assert!((dist - (50.0 - 10.0 * 2.0_f32.sqrt())).abs() < 1e-3);
+        assert!(normal.x < 0.0);
+
+        let square = Collider {
+            shape: Shape::Box {
+                width: 5.0,
+                height: 5.0,
+            },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        let (collides, err, _) = calc_collision(
+            &body(Vec2::new(0.0, 18.0), 0.0),
+            &body(Vec2::ZERO, 0.0),
+            &square,
+            &diamond,
+        );
+        assert!(collides);
+        assert!((err - Vec2::new(0.0, 10.0 * 2.0_f32.sqrt() + 5.0 - 18.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn capsules_and_polygons_collide_and_take_rays() {
+        let capsule = Collider {
+            shape: Shape::Capsule {
+                radius: 4.0,
+                half_length: 6.0,
+            },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        let triangle = Collider {
+            shape: Shape::ConvexPolygon {
+                points: vec![
+                    Vec2::new(-10.0, 0.0),
+                    Vec2::new(10.0, 0.0),
+                    Vec2::new(0.0, 10.0),
+                ],
+            },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: false,
+        };
+        //capsule bottom is at y = 12 - 6 - 4 = 2
+        let standing = body(Vec2::new(0.0, 12.0), 0.0);
+        let (collides, err, normal) =
+            calc_collision(&standing, &body(Vec2::ZERO, 0.0), &capsule, &triangle);
+        assert!(collides);
+        assert!(err.length() > 0.0 && normal.y > 0.0);
+        let (collides, _, _) = calc_collision(
+            &body(Vec2::new(20.0, 12.0), 0.0),
+            &body(Vec2::ZERO, 0.0),
+            &capsule,
+            &triangle,
+        );
+        assert!(!collides);
+
+        let ray = Ray {
+            origin: Vec2::new(0.0, -50.0),
+            direction: Vec2::Y,
+        };
+        let (dist, normal) = triangle.intersect_ray(&ray, Vec2::ZERO).unwrap();
+        assert!((dist - 50.0).abs() < 1e-4);
+        assert!(normal.distance(Vec2::NEG_Y) < 1e-4);
+        let (dist, normal) = capsule.intersect_ray(&ray, Vec2::ZERO).unwrap();
+        assert!((dist - 40.0).abs() < 1e-4);
+        assert!(normal.distance(Vec2::NEG_Y) < 1e-4);
+
+        let (dist, _) = triangle.sweep_circle(&ray, 2.0, Vec2::ZERO).unwrap();
+        assert!((dist - 48.0).abs() < 1e-4);
+        let inside = Ray {
+            origin: Vec2::new(0.0, 2.0),
+            direction: Vec2::Y,
+        };
+        assert!(triangle.intersect_ray(&inside, Vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn force_fields_push_the_right_way() {
+        let field = Collider {
+            shape: Shape::Circle { radius: 100.0 },
+            layer: Layer::WORLD,
+            layer_mask: Layer::ALL,
+            trigger: true,
+        };
+        assert!(field.contains_point(Vec2::ZERO, Vec2::new(50.0, 0.0)));
+        assert!(!field.contains_point(Vec2::ZERO, Vec2::new(150.0, 0.0)));
+
+        let pos = Vec2::new(50.0, 0.0);
+        let magnet = ForceField::Radial { strength: 10.0 };
+        assert!(
+            magnet
+                .acceleration(Vec2::ZERO, pos)
+                .distance(Vec2::new(-10.0, 0.0))
+                < 1e-4
+        );
+        let vortex = ForceField::Vortex { strength: 10.0 };
+        assert!(
+            vortex
+                .acceleration(Vec2::ZERO, pos)
+                .distance(Vec2::new(0.0, 10.0))
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn pendulum_period_is_rate_independent() {
+        let mut half_periods = vec![];
+        for rate in [64.0, 120.0] {
+            let mut pivot = body(Vec2::ZERO, 0.0);
+            pivot.fixed = true;
+            let mut objects = vec![pivot, body(Vec2::new(100.0, 0.0), 0.0)];
+            //the bob swings left until it turns around at the far side
+            let mut time = 0.0;
+            let mut last_x = objects[1].position_current.x;
+            loop {
+                simulate(rate, 1.0 / rate, &mut objects, &[(0, 1, 100.0)]);
+                time += 1.0 / rate;
+                if objects[1].position_current.x > last_x {
+                    break;
+                }
+                last_x = objects[1].position_current.x;
+            }
+            half_periods.push(time);
+        }
+        assert!(
+            (half_periods[0] - half_periods[1]).abs() < half_periods[0] * 0.03,
+            "{:?}",
+            half_periods
+        );
+    }
+
+    #[test]
+    fn sliding_friction_is_sub_step_independent() {
+        let mut speeds = vec![];
+        for sub_steps in [8, 16] {
+            let dt = 1.0 / 64.0 / sub_steps as f32;
+            let mut obj = body(Vec2::ZERO, 0.0);
+            obj.set_velocity(Vec2::new(100.0, 0.0), dt);
+            for _ in 0..(sub_steps * 16) {
+                obj.integrate(dt);
+                apply_friction(Vec2::Y, 0.01, dt, &mut obj);
+            }
+            speeds.push(obj.velocity(dt).x);
+        }
+        assert!(
+            (speeds[0] - speeds[1]).abs() < speeds[0] * 0.02,
+            "{:?}",
+            speeds
+        );
+        //the reference rate keeps the tuned values
+        assert!((fraction_for_dt(0.2, REFERENCE_SUB_STEP_DT) - 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn invalid_physics_configs_are_rejected() {
+        assert!(PhysicsConfig::default().is_valid());
+        for json in [
+            r#"{"sub_steps": 0}"#,
+            r#"{"hand_leash_length": -1.0}"#,
+            r#"{"sleep_speed": -4.0}"#,
+            r#"{"cushion_factor": -0.2}"#,
+            r#"{"stick_iterations": 0}"#,
+        ] {
+            assert!(!set_physics_config_json(json), "{json} was accepted");
+        }
+        assert!(PENDING_PHYSICS_CONFIG.lock().unwrap().is_none());
+        let config = PhysicsConfig {
+            hand_leash_length: f32::NAN,
+            ..PhysicsConfig::default()
+        };
+        assert!(!config.is_valid());
+    }
+
+    #[test]
+    fn layers_must_accept_each_other() {
+        let collider = |layer: Layer, layer_mask: Layer| Collider {
+            shape: Shape::Circle { radius: 1.0 },
+            layer,
+            layer_mask,
+            trigger: false,
+        };
+        let world = collider(Layer::WORLD, Layer::ALL);
+        let player = collider(Layer::PLAYER, Layer::WORLD);
+        let rope = collider(Layer::ROPE, Layer::WORLD);
+        let ghost = collider(Layer::WORLD, Layer::ROPE);
+        assert!(player.interacts_with(&world) && world.interacts_with(&player));
+        assert!(!player.interacts_with(&rope));
+        assert!(!player.interacts_with(&ghost));
+        assert!(rope.interacts_with(&ghost));
+
+        let filter = QueryFilter {
+            layer_mask: Layer::WORLD,
+            layer: Layer::PLAYER,
+            ..QueryFilter::default()
+        };
+        let entity = Entity::from_raw(0);
+        assert!(filter.accepts(entity, &world));
+        assert!(!filter.accepts(entity, &ghost));
+        assert!(!filter.accepts(entity, &rope));
+    }
+
+    fn vec2(range: f32) -> impl Strategy<Value = Vec2> {
+        (-range..range, -range..range).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    //distance from point to the segment start..end
+    fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+        let t = ((point - start).dot(end - start) / (end - start).length_squared()).clamp(0.0, 1.0);
+        (start + (end - start) * t - point).length()
+    }
+
+    #[test]
+    fn circle_on_box_corner_is_not_nan() {
+        let (collides, err, normal) =
+            circle_box_collision(Vec2::new(10.0, 5.0), Vec2::ZERO, 2.0, 10.0, 5.0);
+        assert!(collides);
+        assert!(err.is_finite() && normal.is_finite());
+        assert!((normal.length() - 1.0).abs() < 1e-5);
+    }
+
+    proptest! {
+        #[test]
+        fn circle_circle_separates_and_is_symmetric(
+            pos_a in vec2(100.0),
+            pos_b in vec2(100.0),
+            r_a in 0.5f32..30.0,
+            r_b in 0.5f32..30.0,
+        ) {
+            let (collides, err, normal) = circle_circle_collision(pos_a, pos_b, r_a, r_b);
+            prop_assert!(err.is_finite() && normal.is_finite());
+            if (collides) {
+                prop_assert!((normal.length() - 1.0).abs() < 1e-4);
+                let moved = pos_a + err;
+                prop_assert!(((moved - pos_b).length() - r_a - r_b).abs() < 1e-3);
+            }
+            prop_assume!((pos_a - pos_b).length() > 1e-3);
+            let (collides_swapped, err_swapped, normal_swapped) =
+                circle_circle_collision(pos_b, pos_a, r_b, r_a);
+            prop_assert_eq!(collides, collides_swapped);
+            prop_assert!((err + err_swapped).length() < 1e-3);
+            prop_assert!((normal + normal_swapped).length() < 1e-4);
+        }
+
+        #[test]
+        fn coincident_circles_push_apart(pos in vec2(100.0), r_a in 0.5f32..30.0, r_b in 0.5f32..30.0) {
+            let (collides, err, normal) = circle_circle_collision(pos, pos, r_a, r_b);
+            prop_assert!(collides);
+            prop_assert!(err.is_finite() && normal.is_finite());
+            prop_assert!(((pos + err - pos).length() - r_a - r_b).abs() < 1e-3);
+        }
+
+        #[test]
+        fn circle_box_separates(
+            offset in vec2(1.0),
+            pos_b in vec2(100.0),
+            radius in 0.5f32..30.0,
+            width in 0.5f32..50.0,
+            height in 0.5f32..50.0,
+        ) {
+            //centres from deep inside the box to just out of reach
+            let pos_a = pos_b + offset * (Vec2::new(width, height) + radius + 5.0);
+            let (collides, err, normal) = circle_box_collision(pos_a, pos_b, radius, width, height);
+            prop_assert!(err.is_finite() && normal.is_finite());
+            if (collides) {
+                prop_assert!((normal.length() - 1.0).abs() < 1e-4);
+                let (still_collides, still_err, _) =
+                    circle_box_collision(pos_a + err, pos_b, radius, width, height);
+                prop_assert!(!still_collides || still_err.length() < 1e-3);
+            }
+        }
+
+        #[test]
+        fn circle_box_corners_are_finite(
+            corner in 0usize..4,
+            pos_b in vec2(100.0),
+            radius in 0.0f32..30.0,
+            width in 0.0f32..50.0,
+            height in 0.0f32..50.0,
+        ) {
+            let signs = [Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0), Vec2::new(1.0, -1.0), Vec2::new(-1.0, -1.0)];
+            let pos_a = pos_b + signs[corner] * Vec2::new(width, height);
+            let (_, err, normal) = circle_box_collision(pos_a, pos_b, radius, width, height);
+            prop_assert!(err.is_finite() && normal.is_finite());
+        }
+
+        #[test]
+        fn crossing_segments_meet_in_both_orders(
+            point in vec2(100.0),
+            angle_a in 0.0f32..std::f32::consts::TAU,
+            turn in 0.05f32..(std::f32::consts::PI - 0.05),
+            len_a in 1.0f32..100.0,
+            len_b in 1.0f32..100.0,
+            s0 in 0.01f32..0.99,
+            t0 in 0.01f32..0.99,
+        ) {
+            let dir_a = Vec2::from_angle(angle_a) * len_a;
+            let dir_b = Vec2::from_angle(angle_a + turn) * len_b;
+            let (a1, a2) = (point - dir_a * s0, point + dir_a * (1.0 - s0));
+            let (b1, b2) = (point - dir_b * t0, point + dir_b * (1.0 - t0));
+            let (hit, s) = line_line_intersection(a1, a2, b1, b2, false);
+            prop_assert!(hit);
+            prop_assert!((s - s0).abs() < 1e-3);
+            let (hit, t) = line_line_intersection(b1, b2, a1, a2, false);
+            prop_assert!(hit);
+            prop_assert!((t - t0).abs() < 1e-3);
+        }
+
+        #[test]
+        fn segment_hits_lie_on_both_segments(
+            a1 in vec2(100.0),
+            a2 in vec2(100.0),
+            b1 in vec2(100.0),
+            b2 in vec2(100.0),
+            a_inf: bool,
+        ) {
+            let (hit, s) = line_line_intersection(a1, a2, b1, b2, a_inf);
+            if (!hit) {
+                prop_assert_eq!(s, f32::INFINITY);
+                return Ok(());
+            }
+            prop_assert!(s >= 0.0 && (a_inf || s <= 1.0));
+            let point = a1 + (a2 - a1) * s;
+            prop_assert!(point.is_finite());
+            prop_assert!(segment_distance(point, b1, b2) < 1e-2);
+        }
+
+        #[test]
+        fn ray_towards_box_hits_it(
+            pos in vec2(100.0),
+            size in (0.0f32..50.0, 0.0f32..50.0),
+            origin in vec2(200.0),
+            target in (0.0f32..=1.0, 0.0f32..=1.0),
+            axis_aligned in 0usize..3,
+        ) {
+            let aabb = AABB { pos, size: Vec2::new(size.0, size.1) };
+            let mut target = pos + aabb.size * Vec2::new(target.0, target.1);
+            //rays parallel to an axis leave a zero component in the direction
+            let mut origin = origin;
+            match axis_aligned {
+                1 => origin.x = target.x,
+                2 => origin.y = target.y,
+                _ => {}
+            }
+            if (origin == target) {
+                target += Vec2::X;
+            }
+            let direction = (target - origin).normalize();
+            let (hit, dist) = aabb.intersect_ray(&Ray { origin, direction });
+            let inside = origin.cmpge(aabb.pos).all() && origin.cmple(aabb.pos + aabb.size).all();
+            if (inside) {
+                prop_assert!(hit);
+                prop_assert_eq!(dist, 0.0);
+                return Ok(());
+            }
+            if (target.cmple(aabb.pos + aabb.size).all()) {
+                prop_assert!(hit);
+                prop_assert!(dist <= (target - origin).length() + 1e-3);
+            }
+            if (hit) {
+                let entry = origin + direction * dist;
+                let slack = Vec2::splat(1e-2);
+                prop_assert!(entry.cmpge(aabb.pos - slack).all());
+                prop_assert!(entry.cmple(aabb.pos + aabb.size + slack).all());
+            }
+        }
+
+        #[test]
+        fn ray_box_hits_face_the_ray(
+            center in vec2(100.0),
+            half_size in (0.5f32..50.0, 0.5f32..50.0),
+            origin in vec2(200.0),
+            target in (-1.0f32..=1.0, -1.0f32..=1.0),
+            axis_aligned in 0usize..3,
+        ) {
+            let half_size = Vec2::new(half_size.0, half_size.1);
+            let target = center + half_size * Vec2::new(target.0, target.1);
+            //rays parallel to an axis leave a zero component in the direction
+            let mut origin = origin;
+            match axis_aligned {
+                1 => origin.x = target.x,
+                2 => origin.y = target.y,
+                _ => {}
+            }
+            let outside = (origin - center).abs().cmpgt(half_size).any();
+            prop_assume!(outside);
+            let direction = (target - origin).normalize();
+            let hit = ray_box_intersection(&Ray { origin, direction }, center, half_size);
+            prop_assert!(hit.is_some());
+            let (dist, normal) = hit.unwrap();
+            prop_assert!(dist.is_finite() && dist >= 0.0);
+            prop_assert!(dist <= (target - origin).length() + 1e-3);
+            prop_assert!(normal == Vec2::X || normal == Vec2::NEG_X || normal == Vec2::Y || normal == Vec2::NEG_Y);
+            prop_assert!(normal.dot(direction) < 0.0);
+            let entry = origin + direction * dist;
+            let slack = Vec2::splat(1e-2);
+            prop_assert!((entry - center).abs().cmple(half_size + slack).all());
+        }
+
+        #[test]
+        fn ray_box_with_zero_direction_components_is_never_nan(
+            center in vec2(100.0),
+            half_size in (0.0f32..50.0, 0.0f32..50.0),
+            origin in vec2(200.0),
+            direction in prop::sample::select(vec![Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y, Vec2::ZERO]),
+            on_edge: bool,
+        ) {
+            let half_size = Vec2::new(half_size.0, half_size.1);
+            //an origin on the box edge makes min - origin exactly zero
+            let mut origin = origin;
+            if (on_edge) {
+                if (direction.x == 0.0) {
+                    origin.x = center.x - half_size.x;
+                } else {
+                    origin.y = center.y + half_size.y;
+                }
+            }
+            let hit = ray_box_intersection(&Ray { origin, direction }, center, half_size);
+            if (direction == Vec2::ZERO) {
+                prop_assert!(hit.is_none());
+            }
+            if let Some((dist, normal)) = hit {
+                prop_assert!(dist.is_finite() && dist >= 0.0);
+                prop_assert!((normal.length() - 1.0).abs() < 1e-6);
+                prop_assert!(normal.dot(direction) < 0.0);
+            }
+        }
+
+        #[test]
+        fn degenerate_rays_miss(pos in vec2(100.0), origin in vec2(100.0)) {
+            let aabb = AABB { pos, size: Vec2::splat(10.0) };
+            let (hit, _) = aabb.intersect_ray(&Ray { origin, direction: Vec2::ZERO });
+            prop_assert!(!hit);
+            let (hit, _) = aabb.intersect_ray(&Ray { origin: Vec2::NAN, direction: Vec2::X });
+            prop_assert!(!hit);
+        }
     }
-    return None;
 }
@@ -0,0 +1,193 @@
+use crate::physics::{
+    Collider, CollisionWorld, KDNode, RayLog, Stick, TrackCollision, VerletObject,
+};
+use bevy::app::{App, Plugin, PostUpdate, Update};
+use bevy::color::{Alpha, Color};
+use bevy::gizmos::gizmos::Gizmos;
+use bevy::input::ButtonInput;
+use bevy::prelude::{Commands, IntoSystemConfigs, KeyCode, Query, Res, ResMut, Resource};
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+pub struct PhysicsDebugPlugin;
+
+impl Plugin for PhysicsDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PhysicsDebug {
+            enabled: false,
+            toggle_key: KeyCode::F3,
+        });
+        app.add_systems(Update, toggle_physics_debug_system);
+        app.add_systems(
+            PostUpdate,
+            (
+                draw_collision_tree,
+                draw_colliders,
+                draw_sticks,
+                draw_contacts,
+                draw_rays,
+            )
+                .chain()
+                .run_if(physics_debug_enabled),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct PhysicsDebug {
+    pub enabled: bool,
+    pub toggle_key: KeyCode,
+}
+
+//indexed by the lowest bit of a collider's layer
+const LAYER_COLORS: [Color; 4] = [
+    Color::srgb(0.3, 0.6, 1.0),
+    Color::srgb(0.3, 1.0, 0.4),
+    Color::srgb(1.0, 0.4, 0.9),
+    Color::srgb(1.0, 0.6, 0.2),
+];
+const TRIGGER_COLOR: Color = Color::srgba(1.0, 0.9, 0.2, 0.6);
+const CONTACT_COLOR: Color = Color::WHITE;
+const RAY_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.4);
+const RAY_HIT_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+
+static PENDING_PHYSICS_DEBUG: Lazy<Arc<Mutex<Option<bool>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+#[wasm_bindgen]
+pub fn set_physics_debug(enabled: bool) {
+    if let Ok(mut pending) = PENDING_PHYSICS_DEBUG.lock() {
+        *pending = Some(enabled);
+    }
+}
+
+fn physics_debug_enabled(debug: Res<PhysicsDebug>) -> bool {
+    debug.enabled
+}
+
+//rays are only recorded while the RayLog exists, so it lives and dies with the debug view
+fn toggle_physics_debug_system(
+    mut commands: Commands,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mut debug: ResMut<PhysicsDebug>,
+) {
+    let mut enabled = debug.enabled;
+    if (keys.is_some_and(|keys| keys.just_pressed(debug.toggle_key))) {
+        enabled = !enabled;
+    }
+    if let Some(pending) = PENDING_PHYSICS_DEBUG
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take())
+    {
+        enabled = pending;
+    }
+    if (enabled == debug.enabled) {
+        return;
+    }
+    debug.enabled = enabled;
+    if (enabled) {
+        commands.init_resource::<RayLog>();
+    } else {
+        commands.remove_resource::<RayLog>();
+    }
+}
+
+fn draw_collision_tree(mut gizmos: Gizmos, collision_world: Res<CollisionWorld>) {
+    if let Some(ref node) = collision_world.kd_tree {
+        draw_sub_tree(&mut gizmos, &node.lock().unwrap(), 0);
+    }
+}
+
+fn draw_sub_tree(gizmos: &mut Gizmos, node: &KDNode, depth: usize) {
+    let bounding_box = &node.bounding_box;
+    let color = Color::hsla((depth as f32 * 47.0) % 360.0, 0.8, 0.6, 0.25);
+    gizmos.rect_2d(
+        bounding_box.pos + bounding_box.size / 2.0,
+        bounding_box.size,
+        color,
+    );
+    for child in [&node.left_node, &node.right_node].into_iter().flatten() {
+        draw_sub_tree(gizmos, &child.lock().unwrap(), depth + 1);
+    }
+}
+
+fn draw_colliders(mut gizmos: Gizmos, collider_query: Query<(&Collider, &VerletObject)>) {
+    for (collider, verlet_object) in collider_query.iter() {
+        let mut color = if (collider.trigger) {
+            TRIGGER_COLOR
+        } else {
+            LAYER_COLORS[collider.layer.bits().trailing_zeros() as usize % LAYER_COLORS.len()]
+        };
+        if (verlet_object.sleeping) {
+            color = color.with_alpha(0.3);
+        }
+
+        let (core, radius) = collider.shape.core(verlet_object.position_current);
+        match core.as_slice() {
+            [center] => {
+                gizmos.circle_2d(*center, radius, color);
+            }
+            [start, end] => {
+                let side = (*end - *start).perp().normalize_or_zero() * radius;
+                gizmos.circle_2d(*start, radius, color);
+                gizmos.circle_2d(*end, radius, color);
+                gizmos.line_2d(*start + side, *end + side, color);
+                gizmos.line_2d(*start - side, *end - side, color);
+            }
+            points => {
+                gizmos.linestrip_2d(points.iter().chain(points.first()).copied(), color);
+            }
+        }
+    }
+}
+
+//green at rest length, red once a stick is 10% off
+fn draw_sticks(mut gizmos: Gizmos, stick_query: Query<&Stick>, verlet_query: Query<&VerletObject>) {
+    for stick in stick_query.iter() {
+        let Ok([obj1, obj2]) = verlet_query.get_many([stick.ent1, stick.ent2]) else {
+            continue;
+        };
+        let length = (obj2.position_current - obj1.position_current).length();
+        let strain = ((length - stick.length).abs() / stick.length.max(0.001) / 0.1).min(1.0);
+        gizmos.line_2d(
+            obj1.position_current,
+            obj2.position_current,
+            Color::srgb(strain, 1.0 - strain, 0.2),
+        );
+    }
+}
+
+fn draw_contacts(mut gizmos: Gizmos, tracker_query: Query<&TrackCollision>) {
+    for tracker in tracker_query.iter() {
+        for collision in tracker.collisions.values() {
+            gizmos.arrow_2d(
+                collision.point,
+                collision.point + collision.normal * 16.0,
+                CONTACT_COLOR,
+            );
+        }
+        for collision in tracker.triggers.values() {
+            gizmos.circle_2d(collision.point, 2.0, TRIGGER_COLOR);
+        }
+    }
+}
+
+fn draw_rays(mut gizmos: Gizmos, ray_log: Option<Res<RayLog>>) {
+    let Some(ray_log) = ray_log else {
+        return;
+    };
+    let Ok(mut rays) = ray_log.rays.lock() else {
+        return;
+    };
+    for ray in rays.drain(..) {
+        gizmos.line_2d(ray.origin, ray.end, RAY_COLOR);
+        if (ray.radius > 0.0) {
+            gizmos.circle_2d(ray.end, ray.radius, RAY_COLOR);
+        }
+        if let Some(hit) = ray.hit {
+            gizmos.circle_2d(hit, 3.0, RAY_HIT_COLOR);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::color_picker::GlobalColor;
+use crate::physics::{Stick, VerletObject};
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{Assets, RenderAssetUsages};
+use bevy::color::{Alpha, Color, ColorToComponents, LinearRgba, Mix};
+use bevy::math::Vec2;
+use bevy::prelude::{
+    Added, Commands, Component, Entity, Mesh, Mesh2d, Query, Res, ResMut, Resource,
+};
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::sprite::{AlphaMode2d, ColorMaterial, MeshMaterial2d};
+
+pub struct RopeRenderingPlugin;
+
+impl Plugin for RopeRenderingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RopeRenderSettings {
+            thickness: 3.0,
+            feather: 1.0,
+            tension_color: Color::srgb(1.0, 0.2, 0.2),
+            full_tension: 0.5,
+            slack_color: Color::srgb(0.5, 0.5, 0.5),
+            full_slack: 0.2,
+        });
+        app.add_systems(Update, (setup_rope_mesh, update_rope_mesh));
+    }
+}
+
+#[derive(Resource)]
+pub struct RopeRenderSettings {
+    pub thickness: f32,
+    //width of the transparent edge used for anti-aliasing
+    pub feather: f32,
+    pub tension_color: Color,
+    //stretch at which the rope is fully tinted with tension_color
+    pub full_tension: f32,
+    pub slack_color: Color,
+    //compression at which the rope is fully tinted with slack_color
+    pub full_slack: f32,
+}
+
+//sticks are ordered from the start of the rope to the end
+#[derive(Component)]
+pub struct Rope {
+    pub sticks: Vec<Entity>,
+}
+
+fn setup_rope_mesh(
+    mut commands: Commands,
+    rope_query: Query<Entity, Added<Rope>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for entity in rope_query.iter() {
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        commands.entity(entity).insert((
+            Mesh2d(meshes.add(mesh)),
+            MeshMaterial2d(materials.add(ColorMaterial {
+                color: Color::WHITE,
+                alpha_mode: AlphaMode2d::Blend,
+                texture: None,
+            })),
+        ));
+    }
+}
+
+fn update_rope_mesh(
+    mut commands: Commands,
+    rope_query: Query<(&Rope, &Mesh2d, Entity)>,
+    stick_query: Query<&Stick>,
+    verlet_query: Query<&VerletObject>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<RopeRenderSettings>,
+    global_color: Res<GlobalColor>,
+) {
+    for (rope, mesh_handle, entity) in rope_query.iter() {
+        //split the chain wherever a stick is missing, e.g. after it snapped
+        let mut runs: Vec<Vec<(Vec2, f32)>> = vec![];
+        let mut last_ent: Option<Entity> = None;
+        for stick_ent in &rope.sticks {
+            let Ok(stick) = stick_query.get(*stick_ent) else {
+                last_ent = None;
+                continue;
+            };
+            let Ok([obj1, obj2]) = verlet_query.get_many([stick.ent1, stick.ent2]) else {
+                last_ent = None;
+                continue;
+            };
+            let strain =
+                (obj1.position_current - obj2.position_current).length() / stick.length - 1.0;
+            if (last_ent != Some(stick.ent2)) {
+                runs.push(vec![(obj2.position_current, strain)]);
+            }
+            let run = runs.last_mut().unwrap();
+            let last = run.len() - 1;
+            run[last].1 = (run[last].1 + strain) / 2.0;
+            run.push((obj1.position_current, strain));
+            last_ent = Some(stick.ent1);
+        }
+
+        if (runs.is_empty()) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+            continue;
+        };
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut colors: Vec<[f32; 4]> = vec![];
+        let mut indices: Vec<u32> = vec![];
+        for run in runs {
+            build_ribbon(
+                &run,
+                &settings,
+                global_color.color,
+                &mut positions,
+                &mut colors,
+                &mut indices,
+            );
+        }
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(indices));
+    }
+}
+
+fn strain_color(strain: f32, settings: &RopeRenderSettings, base: Color) -> Color {
+    if (strain >= 0.0) {
+        base.mix(
+            &settings.tension_color,
+            (strain / settings.full_tension).min(1.0),
+        )
+    } else {
+        base.mix(
+            &settings.slack_color,
+            (-strain / settings.full_slack).min(1.0),
+        )
+    }
+}
+
+//every point gets four vertices across the rope: two transparent outer ones for the
+//anti-aliased edge and two opaque inner ones
+fn build_ribbon(
+    points: &Vec<(Vec2, f32)>,
+    settings: &RopeRenderSettings,
+    base: Color,
+    positions: &mut Vec<[f32; 3]>,
+    colors: &mut Vec<[f32; 4]>,
+    indices: &mut Vec<u32>,
+) {
+    if (points.len() < 2) {
+        return;
+    }
+    let half = settings.thickness / 2.0;
+    let offsets = [
+        -(half + settings.feather),
+        -half,
+        half,
+        half + settings.feather,
+    ];
+    let first = positions.len() as u32;
+
+    for i in 0..points.len() {
+        let prev = points[i.saturating_sub(1)].0;
+        let next = points[(i + 1).min(points.len() - 1)].0;
+        let normal = (next - prev).normalize_or_zero().perp();
+        let (pos, strain) = points[i];
+        let color = LinearRgba::from(strain_color(strain, settings, base));
+
+        for (j, offset) in offsets.iter().enumerate() {
+            let vertex = pos + normal * *offset;
+            positions.push([vertex.x, vertex.y, 0.0]);
+            let alpha = if (j == 0 || j == 3) { 0.0 } else { color.alpha };
+            colors.push(color.with_alpha(alpha).to_f32_array());
+        }
+
+        if (i == 0) {
+            continue;
+        }
+        let cur = first + i as u32 * 4;
+        let prev = cur - 4;
+        for j in 0..3 {
+            indices.extend([prev + j, cur + j, prev + j + 1]);
+            indices.extend([prev + j + 1, cur + j, cur + j + 1]);
+        }
+    }
+}
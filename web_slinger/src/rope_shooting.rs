@@ -1,21 +1,42 @@
 use crate::collider_import::CollisionImportPlugin;
 use crate::color_picker::GlobalColor;
 use crate::physics::{
-    raycast, Collider, CollisionSetup, CollisionWorld, Position, Ray, Shape, Stick, VerletObject,
+    BreakableStick, Collider, CollisionSetup, Layer, Position, QueryFilter, Ray, RopeSnapped,
+    Shape, SpatialQuery, Stick, VerletObject, AABB,
 };
+use crate::rope_rendering::Rope;
 use crate::{align_camera_origin, update_scroll_pos, RopeHolder};
 use bevy::app::{App, Plugin, Startup, Update};
 use bevy::input::ButtonInput;
 use bevy::math::Vec2;
 use bevy::prelude::{
-    default, Camera, Commands, Component, Entity, GlobalTransform, MouseButton, Query, Res, Single,
-    Sprite, Transform, Window,
+    default, Camera, Color, Commands, Component, Entity, Event, EventReader, EventWriter,
+    GlobalTransform, IntoSystemConfigs, MouseButton, Query, Res, Resource, Single, Sprite, Time,
+    Transform, Visibility, Window, With,
 };
 
 pub struct RopeShootingPlugin;
 impl Plugin for RopeShootingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (shoot_rope_system, spawn_rope_system));
+        app.add_event::<RopeReleased>();
+        app.add_event::<SnapSound>();
+        app.insert_resource(AimAssist {
+            enabled: false,
+            cone_angle: 0.2,
+            max_distance: 600.0,
+            radius: 6.0,
+        });
+        app.add_systems(
+            Update,
+            (
+                shoot_rope_system,
+                spawn_rope_system,
+                //drops snapped sticks from the shooters before anything releases them
+                rope_snapped_system.before(shoot_rope_system),
+                snap_particle_system,
+                hook_preview_system,
+            ),
+        );
     }
 }
 #[derive(Component)]
@@ -27,6 +48,7 @@ struct RopeSpawner {
     attached_end: Option<Entity>,
     end_length: f32,
     end_fixed: bool,
+    max_stretch: Option<f32>,
     shooter: Entity,
 }
 
@@ -34,7 +56,6 @@ fn spawn_rope_system(
     mut commands: Commands,
     spawner_query: Query<(&RopeSpawner, Entity)>,
     mut shooter_query: Query<&mut RopeShooter>,
-    global_color: Res<GlobalColor>,
 ) {
     for (rope_spawner, entity) in spawner_query.iter() {
         let diff = rope_spawner.end - rope_spawner.start;
@@ -42,6 +63,7 @@ fn spawn_rope_system(
 
         let mut last_ent = rope_spawner.attached_start;
         let mut last_pos = rope_spawner.start;
+        let mut sticks: Vec<Entity> = vec![];
 
         for i in 1..=count {
             let percent = i as f32 / count as f32;
@@ -51,8 +73,8 @@ fn spawn_rope_system(
                 Collider {
                     trigger: false,
                     shape: Shape::Circle { radius: 4.0 },
-                    layer: 1,
-                    layer_mask: 1,
+                    layer: Layer::ROPE,
+                    layer_mask: Layer::WORLD,
                 },
                 VerletObject {
                     fixed: i == count && rope_spawner.end_fixed,
@@ -61,18 +83,21 @@ fn spawn_rope_system(
                     acceleration: Vec2::ZERO,
                     ..default()
                 },
-                Sprite::from_color(global_color.color, Vec2::splat(4.0)),
             ));
             let new_ent = new.id();
             if let Some(last) = last_ent {
-                let stick = commands.spawn(
+                let mut stick = commands.spawn(
                     (Stick {
                         ent1: new_ent,
                         ent2: last,
                         length: (pos - last_pos).length() * 0.9,
                     }),
                 );
+                if let Some(max_stretch) = rope_spawner.max_stretch {
+                    stick.insert(BreakableStick { max_stretch });
+                }
                 let stick_ent = stick.id();
+                sticks.push(stick_ent);
                 if (i == 1) {
                     if let Ok(mut shooter) = shooter_query.get_mut(rope_spawner.shooter) {
                         shooter.connections.push(stick_ent);
@@ -83,14 +108,16 @@ fn spawn_rope_system(
             last_pos = pos;
         }
         if let (Some(end_entity), Some(last_ent)) = (rope_spawner.attached_end, last_ent) {
-            commands.spawn(
+            let stick = commands.spawn(
                 (Stick {
                     ent1: end_entity,
                     ent2: last_ent,
                     length: rope_spawner.end_length,
                 }),
             );
+            sticks.push(stick.id());
         }
+        commands.spawn(Rope { sticks });
         commands.entity(entity).despawn();
     }
 }
@@ -99,20 +126,187 @@ fn spawn_rope_system(
 pub struct RopeShooter {
     pub delete_old: bool,
     pub connections: Vec<Entity>,
+    pub max_stretch: Option<f32>,
+    pub hook_point: Option<Vec2>,
+}
+
+impl RopeShooter {
+    pub fn release(&mut self, commands: &mut Commands) {
+        for con in self.connections.iter() {
+            //a stick that snapped this frame may already be gone
+            if let Some(mut stick) = commands.get_entity(*con) {
+                stick.despawn();
+            }
+        }
+        self.connections.clear();
+        self.hook_point = None;
+    }
+}
+
+#[derive(Event)]
+pub struct RopeReleased {
+    pub shooter: Entity,
+    pub hook_point: Vec2,
 }
 
 #[derive(Component)]
 pub struct Hookable;
 
+//snaps the aim to the hookable closest to the aim direction, cone_angle is in radians
+#[derive(Resource)]
+pub struct AimAssist {
+    pub enabled: bool,
+    pub cone_angle: f32,
+    pub max_distance: f32,
+    //thickness of the ray that is tried before searching the cone
+    pub radius: f32,
+}
+
+#[derive(Component)]
+pub struct HookPreview;
+
+//ropes only attach to the world layer
+fn rope_filter<'a>() -> QueryFilter<'a> {
+    QueryFilter {
+        layer_mask: Layer::WORLD,
+        ..default()
+    }
+}
+
+fn aim_ray(
+    origin: Vec2,
+    direction: Vec2,
+    aim_assist: &AimAssist,
+    spatial_query: &SpatialQuery,
+    hookable_query: &Query<&VerletObject, With<Hookable>>,
+) -> Ray {
+    let ray = Ray { origin, direction };
+    if (!aim_assist.enabled) {
+        return ray;
+    }
+
+    //a fat ray forgives near misses without bending the aim
+    if let Some(hit) = spatial_query.cast_circle(
+        origin,
+        aim_assist.radius,
+        direction,
+        aim_assist.max_distance,
+        &rope_filter(),
+    ) {
+        if (hookable_query.contains(hit.entity)) {
+            if let Some(hit_direction) = (hit.point - origin).try_normalize() {
+                return Ray {
+                    origin,
+                    direction: hit_direction,
+                };
+            }
+        }
+    }
+
+    let is_hookable = |entity: Entity| hookable_query.contains(entity);
+    let search_box = AABB {
+        pos: origin - Vec2::splat(aim_assist.max_distance),
+        size: Vec2::splat(aim_assist.max_distance * 2.0),
+    };
+    let candidates = spatial_query.overlap_aabb(
+        &search_box,
+        &QueryFilter {
+            predicate: Some(&is_hookable),
+            ..rope_filter()
+        },
+    );
+
+    let mut best_angle = aim_assist.cone_angle;
+    let mut best_direction: Option<Vec2> = None;
+    for candidate in candidates {
+        let Ok(hookable) = hookable_query.get(candidate) else {
+            continue;
+        };
+        let diff = hookable.position_current - origin;
+        if (diff.length() > aim_assist.max_distance || diff.length() == 0.0) {
+            continue;
+        }
+        let angle = direction.angle_to(diff).abs();
+        if (angle < best_angle) {
+            best_angle = angle;
+            best_direction = Some(diff.normalize());
+        }
+    }
+
+    let Some(best_direction) = best_direction else {
+        return ray;
+    };
+    let assisted = Ray {
+        origin,
+        direction: best_direction,
+    };
+    //only snap if nothing blocks the way to a hookable
+    if let Some(hit) = spatial_query.cast_ray(&assisted, &rope_filter()) {
+        if (hookable_query.contains(hit.entity)) {
+            return assisted;
+        }
+    }
+    ray
+}
+
+fn hook_preview_system(
+    player_query: Query<(&VerletObject, &RopeHolder)>,
+    hand_query: Query<&VerletObject, With<RopeShooter>>,
+    mouse_pos_query: Query<&Position>,
+    spatial_query: SpatialQuery,
+    hookable_query: Query<&VerletObject, With<Hookable>>,
+    aim_assist: Res<AimAssist>,
+    global_color: Res<GlobalColor>,
+    mut preview_query: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<HookPreview>>,
+) {
+    for (player_object, rope_holder) in player_query.iter() {
+        let Ok((mut transform, mut sprite, mut visibility)) =
+            preview_query.get_mut(rope_holder.hook_preview)
+        else {
+            continue;
+        };
+        *visibility = Visibility::Hidden;
+        let (Ok(hand_object), Ok(mouse_pos)) = (
+            hand_query.get(rope_holder.hand),
+            mouse_pos_query.get(rope_holder.mouse),
+        ) else {
+            continue;
+        };
+        let direction = (mouse_pos.pos - player_object.position_current).normalize_or_zero();
+        if (direction == Vec2::ZERO) {
+            continue;
+        }
+        let ray = aim_ray(
+            hand_object.position_current,
+            direction,
+            &aim_assist,
+            &spatial_query,
+            &hookable_query,
+        );
+        let Some(hit) = spatial_query.cast_ray(&ray, &rope_filter()) else {
+            continue;
+        };
+        transform.translation.x = hit.point.x;
+        transform.translation.y = hit.point.y;
+        sprite.color = if hookable_query.contains(hit.entity) {
+            global_color.color
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.3)
+        };
+        *visibility = Visibility::Visible;
+    }
+}
+
 fn shoot_rope_system(
     mut commands: Commands,
     buttons: Res<ButtonInput<MouseButton>>,
     player_query: Query<(&VerletObject, &RopeHolder)>,
     mut hand_query: Query<(&VerletObject, &mut RopeShooter)>,
     mouse_pos_query: Query<&Position>,
-    collision_world: Res<CollisionWorld>,
-    collider_query: Query<(&Collider, &VerletObject)>,
-    hookable_query: Query<&Hookable>,
+    spatial_query: SpatialQuery,
+    hookable_query: Query<&VerletObject, With<Hookable>>,
+    aim_assist: Res<AimAssist>,
+    mut released_events: EventWriter<RopeReleased>,
 ) {
     let mut clear = false;
     let mut shoot = false;
@@ -132,24 +326,33 @@ fn shoot_rope_system(
         let entity = rope_holder.hand;
         if let Ok((verlet_object, mut shooter)) = hand_query.get_mut(entity) {
             if (clear) {
-                for con in shooter.connections.iter() {
-                    commands.entity(*con).despawn();
+                if let Some(hook_point) = shooter.hook_point {
+                    if (buttons.just_released(MouseButton::Left)) {
+                        released_events.send(RopeReleased {
+                            shooter: entity,
+                            hook_point,
+                        });
+                    }
                 }
-                shooter.connections.clear();
+                shooter.release(&mut commands);
             }
             if (shoot) {
                 let Ok(mouse_pos) = mouse_pos_query.get(rope_holder.mouse) else {
                     continue;
                 };
                 let point = mouse_pos.pos;
-                let ray = Ray {
-                    origin: verlet_object.position_current,
-                    direction: (point - player_object.position_current).normalize(),
-                };
-                let hit = raycast(&ray, &collider_query, &collision_world);
+                let ray = aim_ray(
+                    verlet_object.position_current,
+                    (point - player_object.position_current).normalize(),
+                    &aim_assist,
+                    &spatial_query,
+                    &hookable_query,
+                );
+                let hit = spatial_query.cast_ray(&ray, &rope_filter());
                 if let Some(hit) = hit {
-                    if let Ok(hookedEnt) = hookable_query.get(hit.1) {
-                        let pos = ray.origin + hit.0 * ray.direction;
+                    if (hookable_query.contains(hit.entity)) {
+                        let pos = hit.point;
+                        shooter.hook_point = Some(pos);
                         commands.spawn(
                             (RopeSpawner {
                                 start: verlet_object.position_current,
@@ -159,6 +362,7 @@ fn shoot_rope_system(
                                 attached_end: None,
                                 end_length: 0.0,
                                 end_fixed: true,
+                                max_stretch: shooter.max_stretch,
                                 shooter: entity,
                             }),
                         );
@@ -168,3 +372,74 @@ fn shoot_rope_system(
         }
     }
 }
+
+//hook for a snap sound, sent once per snapped stick
+#[derive(Event)]
+pub struct SnapSound {
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+struct SnapParticle {
+    velocity: Vec2,
+    lifetime: f32,
+}
+
+pub(crate) fn rope_snapped_system(
+    mut commands: Commands,
+    mut snapped_events: EventReader<RopeSnapped>,
+    mut sound_events: EventWriter<SnapSound>,
+    mut shooter_query: Query<&mut RopeShooter>,
+    rope_query: Query<&Rope>,
+    global_color: Res<GlobalColor>,
+) {
+    for snapped in snapped_events.read() {
+        //the shooter only holds the first stick, a break anywhere along its rope cuts it off from the hook
+        let snapped_rope = rope_query
+            .iter()
+            .find(|rope| rope.sticks.contains(&snapped.stick))
+            .map(|rope| rope.sticks.clone())
+            .unwrap_or_else(|| vec![snapped.stick]);
+        for mut shooter in shooter_query.iter_mut() {
+            if (shooter
+                .connections
+                .iter()
+                .any(|con| snapped_rope.contains(con)))
+            {
+                shooter.hook_point = None;
+            }
+            //the stick is already gone, so the shooter must not despawn it again
+            shooter.connections.retain(|con| *con != snapped.stick);
+        }
+
+        sound_events.send(SnapSound {
+            position: snapped.position,
+        });
+        for i in 0..6 {
+            let angle = i as f32 / 6.0 * std::f32::consts::TAU;
+            commands.spawn((
+                SnapParticle {
+                    velocity: Vec2::from_angle(angle) * 60.0,
+                    lifetime: 0.3,
+                },
+                Transform::from_xyz(snapped.position.x, snapped.position.y, 2.0),
+                Sprite::from_color(global_color.color, Vec2::splat(2.0)),
+            ));
+        }
+    }
+}
+
+fn snap_particle_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particle_query: Query<(&mut SnapParticle, &mut Transform, Entity)>,
+) {
+    for (mut particle, mut transform, entity) in particle_query.iter_mut() {
+        particle.lifetime -= time.delta_secs();
+        if (particle.lifetime <= 0.0) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_secs()).extend(0.0);
+    }
+}
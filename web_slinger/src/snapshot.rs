@@ -0,0 +1,821 @@
+use crate::movement::MovementState;
+use crate::physics::{
+    BreakableStick, Collider, Collision, Kinematic, StaticCollider, Stick, TrackCollision,
+    VerletObject,
+};
+use crate::rope_rendering::Rope;
+use crate::rope_shooting::RopeShooter;
+use crate::timer::TimeTracker;
+use bevy::app::{App, Plugin, PostStartup, Update};
+use bevy::input::ButtonInput;
+use bevy::math::Vec2;
+use bevy::prelude::{
+    Component, Entity, IntoSystemConfigs, KeyCode, Resource, Transform, With, Without, World,
+};
+use bevy::utils::{HashMap, HashSet};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Snapshots {
+            checkpoint: None,
+            save_key: KeyCode::F5,
+            load_key: KeyCode::F9,
+        });
+        app.add_systems(PostStartup, (assign_snapshot_ids, record_layout).chain());
+        app.add_systems(Update, snapshot_system);
+    }
+}
+
+#[derive(Resource)]
+pub struct Snapshots {
+    pub checkpoint: Option<PhysicsSnapshot>,
+    pub save_key: KeyCode,
+    pub load_key: KeyCode,
+}
+
+//stable id of a body that exists from startup on, entities change between page loads
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SnapshotId(pub u32);
+
+//bodies without an id, like the player and page colliders, are numbered by where they start.
+//startup systems can spawn in any order, the page layout is the same every time
+pub fn assign_snapshot_ids(world: &mut World) {
+    assign_snapshot_ids_except(world, &HashSet::new());
+}
+
+fn assign_snapshot_ids_except(world: &mut World, skip: &HashSet<Entity>) {
+    let mut next = world
+        .query::<&SnapshotId>()
+        .iter(world)
+        .map(|id| id.0 + 1)
+        .max()
+        .unwrap_or(0);
+    let mut unassigned: Vec<(Vec2, Entity)> = world
+        .query_filtered::<(Entity, &VerletObject), Without<SnapshotId>>()
+        .iter(world)
+        .filter(|(entity, _)| !skip.contains(entity))
+        .map(|(entity, verlet_object)| (verlet_object.position_current, entity))
+        .collect();
+    unassigned.sort_by(|(a, a_entity), (b, b_entity)| {
+        a.x.total_cmp(&b.x)
+            .then(a.y.total_cmp(&b.y))
+            .then(a_entity.cmp(b_entity))
+    });
+    for (_, entity) in unassigned {
+        world.entity_mut(entity).insert(SnapshotId(next));
+        next += 1;
+    }
+}
+
+//ids only point at the same bodies if the page laid out the same way, the plain static colliders
+//are the page so their count and extents tell layouts apart
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct LayoutFingerprint {
+    statics: usize,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl LayoutFingerprint {
+    pub fn of(world: &mut World) -> Self {
+        let mut static_query =
+            world.query_filtered::<&VerletObject, (With<StaticCollider>, Without<Kinematic>)>();
+        let positions: Vec<Vec2> = static_query
+            .iter(world)
+            .map(|verlet_object| verlet_object.position_current)
+            .collect();
+        if (positions.is_empty()) {
+            return Self {
+                statics: 0,
+                min: Vec2::ZERO,
+                max: Vec2::ZERO,
+            };
+        }
+        Self {
+            statics: positions.len(),
+            min: positions.iter().copied().reduce(Vec2::min).unwrap(),
+            max: positions.iter().copied().reduce(Vec2::max).unwrap(),
+        }
+    }
+
+    //json round trips and float noise should not reject a snapshot of the same page
+    pub fn matches(&self, other: &Self) -> bool {
+        self.statics == other.statics
+            && self.min.distance(other.min) < 0.5
+            && self.max.distance(other.max) < 0.5
+    }
+}
+
+//layout of the running page, known after startup so load_snapshot_json can check without the world
+static CURRENT_LAYOUT: Lazy<Arc<Mutex<Option<LayoutFingerprint>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+fn record_layout(world: &mut World) {
+    let layout = LayoutFingerprint::of(world);
+    if let Ok(mut current) = CURRENT_LAYOUT.lock() {
+        *current = Some(layout);
+    }
+}
+
+//everything refers to bodies by id or by their place in a rope, so a snapshot can be loaded into another world.
+//the snapshot owns the ropes, their sticks and their segments, everything else is only written to
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PhysicsSnapshot {
+    layout: LayoutFingerprint,
+    bodies: Vec<BodySnapshot>,
+    ropes: Vec<RopeSnapshot>,
+    shooters: Vec<ShooterSnapshot>,
+    trackers: Vec<TrackerSnapshot>,
+    time_tracker: Option<TimeTrackerSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+enum BodyRef {
+    Id(SnapshotId),
+    Segment { rope: usize, index: usize },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct BodySnapshot {
+    id: SnapshotId,
+    verlet_object: VerletObject,
+    kinematic: Option<KinematicSnapshot>,
+    movement: Option<MovementState>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct KinematicSnapshot {
+    time: f32,
+    step: Vec2,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct RopeSnapshot {
+    segments: Vec<SegmentSnapshot>,
+    sticks: Vec<StickSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SegmentSnapshot {
+    verlet_object: VerletObject,
+    collider: Option<Collider>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct StickSnapshot {
+    ent1: BodyRef,
+    ent2: BodyRef,
+    length: f32,
+    max_stretch: Option<f32>,
+}
+
+//connections are (rope, stick) indices
+#[derive(Serialize, Deserialize, Clone)]
+struct ShooterSnapshot {
+    id: SnapshotId,
+    connections: Vec<(usize, usize)>,
+    hook_point: Option<Vec2>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TrackerSnapshot {
+    id: SnapshotId,
+    collisions: Vec<(BodyRef, Collision)>,
+    triggers: Vec<(BodyRef, Collision)>,
+    last: Vec<(BodyRef, Collision)>,
+    last_triggers: Vec<(BodyRef, Collision)>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct TimeTrackerSnapshot {
+    time: f32,
+    active: bool,
+    finish_time: f32,
+    show_finish_screen: bool,
+}
+
+impl PhysicsSnapshot {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+//plain static colliders never change, so only moving bodies and kinematic colliders are stored
+pub fn capture_snapshot(world: &mut World) -> PhysicsSnapshot {
+    //bodies spawned after startup get an id now, rope segments are stored with their rope instead
+    let segments: HashSet<Entity> = world
+        .query::<&Rope>()
+        .iter(world)
+        .flat_map(|rope| rope.sticks.iter())
+        .filter_map(|stick_entity| world.get::<Stick>(*stick_entity))
+        .flat_map(|stick| [stick.ent1, stick.ent2])
+        .collect();
+    assign_snapshot_ids_except(world, &segments);
+
+    let mut refs: HashMap<Entity, BodyRef> = world
+        .query::<(Entity, &SnapshotId)>()
+        .iter(world)
+        .map(|(entity, id)| (entity, BodyRef::Id(*id)))
+        .collect();
+
+    let mut body_query = world.query::<(
+        &SnapshotId,
+        &VerletObject,
+        Option<&Kinematic>,
+        Option<&MovementState>,
+        Option<&StaticCollider>,
+    )>();
+    let bodies = body_query
+        .iter(world)
+        .filter(|(_, _, kinematic, _, static_collider)| {
+            kinematic.is_some() || static_collider.is_none()
+        })
+        .map(|(id, verlet_object, kinematic, movement, _)| BodySnapshot {
+            id: *id,
+            verlet_object: verlet_object.clone(),
+            kinematic: kinematic.map(|kinematic| KinematicSnapshot {
+                time: kinematic.time,
+                step: kinematic.step,
+            }),
+            movement: movement.cloned(),
+        })
+        .collect();
+
+    let rope_sticks: Vec<Vec<Entity>> = world
+        .query::<&Rope>()
+        .iter(world)
+        .map(|rope| rope.sticks.clone())
+        .collect();
+    let mut ropes = vec![];
+    let mut stick_refs: HashMap<Entity, (usize, usize)> = HashMap::new();
+    for (rope_index, sticks) in rope_sticks.iter().enumerate() {
+        let mut rope = RopeSnapshot {
+            segments: vec![],
+            sticks: vec![],
+        };
+        for stick_entity in sticks {
+            let Some(stick) = world.get::<Stick>(*stick_entity) else {
+                continue;
+            };
+            let mut ends = vec![];
+            for entity in [stick.ent1, stick.ent2] {
+                if let Some(body_ref) = refs.get(&entity) {
+                    ends.push(*body_ref);
+                    continue;
+                }
+                let Some(verlet_object) = world.get::<VerletObject>(entity) else {
+                    break;
+                };
+                let body_ref = BodyRef::Segment {
+                    rope: rope_index,
+                    index: rope.segments.len(),
+                };
+                rope.segments.push(SegmentSnapshot {
+                    verlet_object: verlet_object.clone(),
+                    collider: world.get::<Collider>(entity).cloned(),
+                });
+                refs.insert(entity, body_ref);
+                ends.push(body_ref);
+            }
+            let [ent1, ent2] = ends[..] else {
+                continue;
+            };
+            stick_refs.insert(*stick_entity, (rope_index, rope.sticks.len()));
+            rope.sticks.push(StickSnapshot {
+                ent1,
+                ent2,
+                length: stick.length,
+                max_stretch: world
+                    .get::<BreakableStick>(*stick_entity)
+                    .map(|breakable| breakable.max_stretch),
+            });
+        }
+        ropes.push(rope);
+    }
+
+    let mut shooter_query = world.query::<(&SnapshotId, &RopeShooter)>();
+    let shooters = shooter_query
+        .iter(world)
+        .map(|(id, shooter)| ShooterSnapshot {
+            id: *id,
+            connections: shooter
+                .connections
+                .iter()
+                .filter_map(|stick| stick_refs.get(stick).copied())
+                .collect(),
+            hook_point: shooter.hook_point,
+        })
+        .collect();
+
+    let to_vec = |map: &HashMap<Entity, Collision>| {
+        map.iter()
+            .filter_map(|(e, c)| refs.get(e).map(|body_ref| (*body_ref, *c)))
+            .collect()
+    };
+    let mut tracker_query = world.query::<(&SnapshotId, &TrackCollision)>();
+    let trackers = tracker_query
+        .iter(world)
+        .map(|(id, tracker)| TrackerSnapshot {
+            id: *id,
+            collisions: to_vec(&tracker.collisions),
+            triggers: to_vec(&tracker.triggers),
+            last: to_vec(&tracker.last),
+            last_triggers: to_vec(&tracker.last_triggers),
+        })
+        .collect();
+
+    let time_tracker =
+        world
+            .get_resource::<TimeTracker>()
+            .map(|time_tracker| TimeTrackerSnapshot {
+                time: time_tracker.time,
+                active: time_tracker.active.value,
+                finish_time: time_tracker.finish_time,
+                show_finish_screen: time_tracker.show_finish_screen.value,
+            });
+
+    PhysicsSnapshot {
+        layout: LayoutFingerprint::of(world),
+        bodies,
+        ropes,
+        shooters,
+        trackers,
+        time_tracker,
+    }
+}
+
+//the current ropes are replaced by the snapshot's, bodies with an id are written in place.
+//a snapshot of another layout is refused, its ids would point at other bodies
+pub fn restore_snapshot(world: &mut World, snapshot: &PhysicsSnapshot) -> bool {
+    if (!snapshot.layout.matches(&LayoutFingerprint::of(world))) {
+        return false;
+    }
+    let live: HashMap<SnapshotId, Entity> = world
+        .query::<(Entity, &SnapshotId)>()
+        .iter(world)
+        .map(|(entity, id)| (*id, entity))
+        .collect();
+
+    //only the ropes, their sticks and the segments in between belong to the snapshot
+    let mut owned = vec![];
+    let mut rope_query = world.query::<(Entity, &Rope)>();
+    for (rope_entity, rope) in rope_query.iter(world) {
+        owned.push(rope_entity);
+        for stick_entity in &rope.sticks {
+            let Some(stick) = world.get::<Stick>(*stick_entity) else {
+                continue;
+            };
+            owned.push(*stick_entity);
+            for end in [stick.ent1, stick.ent2] {
+                if (world.get::<SnapshotId>(end).is_none()
+                    && world.get::<VerletObject>(end).is_some())
+                {
+                    owned.push(end);
+                }
+            }
+        }
+    }
+    owned.sort();
+    owned.dedup();
+    for entity in owned {
+        world.despawn(entity);
+    }
+
+    let segments: Vec<Vec<Entity>> = snapshot
+        .ropes
+        .iter()
+        .map(|rope| {
+            rope.segments
+                .iter()
+                .map(|segment| {
+                    let pos = segment.verlet_object.position_current;
+                    let mut new = world.spawn((
+                        Transform::from_xyz(pos.x, pos.y, 0.0),
+                        segment.verlet_object.clone(),
+                    ));
+                    if let Some(ref collider) = segment.collider {
+                        new.insert(collider.clone());
+                    }
+                    new.id()
+                })
+                .collect()
+        })
+        .collect();
+    let resolve = |body_ref: &BodyRef| match body_ref {
+        BodyRef::Id(id) => live.get(id).copied(),
+        BodyRef::Segment { rope, index } => segments
+            .get(*rope)
+            .and_then(|rope| rope.get(*index))
+            .copied(),
+    };
+
+    let mut rope_sticks: Vec<Vec<Option<Entity>>> = vec![];
+    for rope in &snapshot.ropes {
+        let mut sticks = vec![];
+        for stick in &rope.sticks {
+            let (Some(ent1), Some(ent2)) = (resolve(&stick.ent1), resolve(&stick.ent2)) else {
+                sticks.push(None);
+                continue;
+            };
+            let mut new = world.spawn(Stick {
+                ent1,
+                ent2,
+                length: stick.length,
+            });
+            if let Some(max_stretch) = stick.max_stretch {
+                new.insert(BreakableStick { max_stretch });
+            }
+            sticks.push(Some(new.id()));
+        }
+        world.spawn(Rope {
+            sticks: sticks.iter().flatten().copied().collect(),
+        });
+        rope_sticks.push(sticks);
+    }
+
+    //written last, spawning the sticks wakes the bodies they connect
+    for (rope, entities) in snapshot.ropes.iter().zip(&segments) {
+        for (segment, entity) in rope.segments.iter().zip(entities) {
+            if let Some(mut verlet_object) = world.get_mut::<VerletObject>(*entity) {
+                *verlet_object = segment.verlet_object.clone();
+            }
+        }
+    }
+    for body in &snapshot.bodies {
+        let Some(&entity) = live.get(&body.id) else {
+            continue;
+        };
+        if let Some(mut verlet_object) = world.get_mut::<VerletObject>(entity) {
+            *verlet_object = body.verlet_object.clone();
+        }
+        if let (Some(ref saved), Some(mut kinematic)) =
+            (&body.kinematic, world.get_mut::<Kinematic>(entity))
+        {
+            kinematic.time = saved.time;
+            kinematic.step = saved.step;
+        }
+        if let (Some(ref saved), Some(mut movement)) =
+            (&body.movement, world.get_mut::<MovementState>(entity))
+        {
+            *movement = saved.clone();
+        }
+    }
+
+    for shooter_snapshot in &snapshot.shooters {
+        let Some(&entity) = live.get(&shooter_snapshot.id) else {
+            continue;
+        };
+        if let Some(mut shooter) = world.get_mut::<RopeShooter>(entity) {
+            shooter.connections = shooter_snapshot
+                .connections
+                .iter()
+                .filter_map(|(rope, stick)| {
+                    rope_sticks
+                        .get(*rope)
+                        .and_then(|sticks| sticks.get(*stick))
+                        .copied()
+                        .flatten()
+                })
+                .collect();
+            shooter.hook_point = shooter_snapshot.hook_point;
+        }
+    }
+
+    let to_map = |collisions: &Vec<(BodyRef, Collision)>| {
+        collisions
+            .iter()
+            .filter_map(|(other, collision)| resolve(other).map(|other| (other, *collision)))
+            .collect::<HashMap<Entity, Collision>>()
+    };
+    for tracker_snapshot in &snapshot.trackers {
+        let Some(&entity) = live.get(&tracker_snapshot.id) else {
+            continue;
+        };
+        if let Some(mut tracker) = world.get_mut::<TrackCollision>(entity) {
+            tracker.collisions = to_map(&tracker_snapshot.collisions);
+            tracker.triggers = to_map(&tracker_snapshot.triggers);
+            tracker.last = to_map(&tracker_snapshot.last);
+            tracker.last_triggers = to_map(&tracker_snapshot.last_triggers);
+        }
+    }
+
+    if let (Some(ref saved), Some(mut time_tracker)) = (
+        &snapshot.time_tracker,
+        world.get_resource_mut::<TimeTracker>(),
+    ) {
+        time_tracker.time = saved.time;
+        time_tracker.active.set(saved.active);
+        time_tracker.finish_time = saved.finish_time;
+        time_tracker
+            .show_finish_screen
+            .set(saved.show_finish_screen);
+    }
+    true
+}
+
+static PENDING_SNAPSHOT: Lazy<Arc<Mutex<Option<PhysicsSnapshot>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+static SAVED_SNAPSHOT_JSON: Lazy<Arc<Mutex<Option<String>>>> =
+    Lazy::new(|| Arc::new(Mutex::new(None)));
+
+//json of the last checkpoint, so the page can keep it around
+#[wasm_bindgen]
+pub fn saved_snapshot_json() -> Option<String> {
+    SAVED_SNAPSHOT_JSON.lock().ok()?.clone()
+}
+
+#[wasm_bindgen]
+pub fn load_snapshot_json(json: &str) -> bool {
+    let Ok(snapshot) = PhysicsSnapshot::from_json(json) else {
+        return false;
+    };
+    let layout_matches = CURRENT_LAYOUT
+        .lock()
+        .ok()
+        .and_then(|current| *current)
+        .map_or(true, |current| current.matches(&snapshot.layout));
+    if (!layout_matches) {
+        return false;
+    }
+    if let Ok(mut pending) = PENDING_SNAPSHOT.lock() {
+        *pending = Some(snapshot);
+    }
+    true
+}
+
+fn snapshot_system(world: &mut World) {
+    let (save, load) = match (
+        world.get_resource::<ButtonInput<KeyCode>>(),
+        world.get_resource::<Snapshots>(),
+    ) {
+        (Some(keys), Some(snapshots)) => (
+            keys.just_pressed(snapshots.save_key),
+            keys.just_pressed(snapshots.load_key),
+        ),
+        _ => (false, false),
+    };
+
+    if (save) {
+        let snapshot = capture_snapshot(world);
+        if let (Ok(json), Ok(mut saved)) = (snapshot.to_json(), SAVED_SNAPSHOT_JSON.lock()) {
+            *saved = Some(json);
+        }
+        world.resource_mut::<Snapshots>().checkpoint = Some(snapshot);
+    }
+
+    let pending = PENDING_SNAPSHOT
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take());
+    if let Some(snapshot) = pending {
+        if (restore_snapshot(world, &snapshot)) {
+            world.resource_mut::<Snapshots>().checkpoint = Some(snapshot);
+        }
+    } else if (load) {
+        if let Some(snapshot) = world.resource::<Snapshots>().checkpoint.clone() {
+            restore_snapshot(world, &snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::KinematicPath;
+
+    fn body(world: &mut World, pos: Vec2) -> Entity {
+        world
+            .spawn((
+                Transform::from_xyz(pos.x, pos.y, 0.0),
+                VerletObject {
+                    position_current: pos,
+                    position_old: pos,
+                    ..Default::default()
+                },
+            ))
+            .id()
+    }
+
+    #[test]
+    fn restore_brings_back_despawned_rope() {
+        let mut world = World::new();
+        let anchor = body(&mut world, Vec2::ZERO);
+        assign_snapshot_ids(&mut world);
+        let node = body(&mut world, Vec2::new(0.0, -10.0));
+        let stick = world
+            .spawn(Stick {
+                ent1: anchor,
+                ent2: node,
+                length: 10.0,
+            })
+            .id();
+        world.spawn(Rope {
+            sticks: vec![stick],
+        });
+
+        let json = capture_snapshot(&mut world).to_json().unwrap();
+
+        world.despawn(stick);
+        world.despawn(node);
+        world
+            .get_mut::<VerletObject>(anchor)
+            .unwrap()
+            .position_current = Vec2::new(50.0, 0.0);
+
+        assert!(restore_snapshot(
+            &mut world,
+            &PhysicsSnapshot::from_json(&json).unwrap()
+        ));
+
+        assert_eq!(
+            world.get::<VerletObject>(anchor).unwrap().position_current,
+            Vec2::ZERO
+        );
+        let sticks: Vec<&Stick> = world.query::<&Stick>().iter(&world).collect();
+        assert_eq!(sticks.len(), 1);
+        assert_eq!(sticks[0].ent1, anchor);
+        let node = sticks[0].ent2;
+        assert_eq!(
+            world.get::<VerletObject>(node).unwrap().position_current,
+            Vec2::new(0.0, -10.0)
+        );
+        let rope = world.query::<&Rope>().single(&world);
+        assert_eq!(rope.sticks.len(), 1);
+    }
+
+    #[test]
+    fn restore_keeps_what_the_snapshot_does_not_own() {
+        let mut world = World::new();
+        let anchor = body(&mut world, Vec2::ZERO);
+        assign_snapshot_ids(&mut world);
+        let snapshot = capture_snapshot(&mut world);
+
+        let stranger = body(&mut world, Vec2::new(20.0, 0.0));
+        let tether = world
+            .spawn(Stick {
+                ent1: anchor,
+                ent2: stranger,
+                length: 20.0,
+            })
+            .id();
+        assert!(restore_snapshot(&mut world, &snapshot));
+
+        assert!(world.get::<VerletObject>(stranger).is_some());
+        assert!(world.get::<Stick>(tether).is_some());
+    }
+
+    //the player, a moving platform and a rope hanging from the player, after padding shifts the entities
+    fn level(world: &mut World, padding: usize) -> (Entity, Entity) {
+        for _ in 0..padding {
+            world.spawn_empty();
+        }
+        let player = world
+            .spawn((
+                VerletObject::default(),
+                MovementState::default(),
+                RopeShooter {
+                    delete_old: true,
+                    connections: vec![],
+                    max_stretch: None,
+                    hook_point: None,
+                },
+            ))
+            .id();
+        let platform = world
+            .spawn((
+                StaticCollider,
+                VerletObject {
+                    fixed: true,
+                    ..Default::default()
+                },
+                Kinematic::new(KinematicPath::Follow { target: Vec2::ZERO }),
+            ))
+            .id();
+        assign_snapshot_ids(world);
+        (player, platform)
+    }
+
+    #[test]
+    fn snapshot_loads_into_a_fresh_world() {
+        let mut saved = World::new();
+        let (player, platform) = level(&mut saved, 0);
+        {
+            let mut movement = saved.get_mut::<MovementState>(player).unwrap();
+            movement.dashes_left = 0;
+            movement.zip_target = Some(Vec2::new(5.0, 5.0));
+        }
+        {
+            let mut kinematic = saved.get_mut::<Kinematic>(platform).unwrap();
+            kinematic.time = 1.5;
+            kinematic.step = Vec2::new(2.0, 0.0);
+        }
+        let node = body(&mut saved, Vec2::new(0.0, -10.0));
+        let stick = saved
+            .spawn(Stick {
+                ent1: player,
+                ent2: node,
+                length: 10.0,
+            })
+            .id();
+        saved.spawn(Rope {
+            sticks: vec![stick],
+        });
+        saved.get_mut::<RopeShooter>(player).unwrap().connections = vec![stick];
+        let json = capture_snapshot(&mut saved).to_json().unwrap();
+
+        let mut world = World::new();
+        let (player, platform) = level(&mut world, 7);
+        assert!(restore_snapshot(
+            &mut world,
+            &PhysicsSnapshot::from_json(&json).unwrap()
+        ));
+
+        let movement = world.get::<MovementState>(player).unwrap();
+        assert_eq!(movement.dashes_left, 0);
+        assert_eq!(movement.zip_target, Some(Vec2::new(5.0, 5.0)));
+        let kinematic = world.get::<Kinematic>(platform).unwrap();
+        assert_eq!((kinematic.time, kinematic.step), (1.5, Vec2::new(2.0, 0.0)));
+        let shooter = world.get::<RopeShooter>(player).unwrap();
+        assert_eq!(shooter.connections.len(), 1);
+        let stick = world.get::<Stick>(shooter.connections[0]).unwrap();
+        assert_eq!(stick.ent1, player);
+        assert_eq!(
+            world
+                .get::<VerletObject>(stick.ent2)
+                .unwrap()
+                .position_current,
+            Vec2::new(0.0, -10.0)
+        );
+    }
+
+    fn page_box(world: &mut World, pos: Vec2) {
+        world.spawn((
+            StaticCollider,
+            VerletObject {
+                position_current: pos,
+                position_old: pos,
+                fixed: true,
+                ..Default::default()
+            },
+        ));
+    }
+
+    #[test]
+    fn snapshot_of_another_layout_is_refused() {
+        let mut saved = World::new();
+        page_box(&mut saved, Vec2::ZERO);
+        let player = body(&mut saved, Vec2::new(0.0, 20.0));
+        assign_snapshot_ids(&mut saved);
+        let json = capture_snapshot(&mut saved).to_json().unwrap();
+
+        //same page after a resize, the text wrapped somewhere else
+        let mut world = World::new();
+        page_box(&mut world, Vec2::new(0.0, 40.0));
+        body(&mut world, Vec2::new(0.0, 20.0));
+        assign_snapshot_ids(&mut world);
+        let snapshot = PhysicsSnapshot::from_json(&json).unwrap();
+        assert!(!snapshot.layout.matches(&LayoutFingerprint::of(&mut world)));
+        assert!(!restore_snapshot(&mut world, &snapshot));
+
+        saved
+            .get_mut::<VerletObject>(player)
+            .unwrap()
+            .position_current = Vec2::new(9.0, 9.0);
+        assert!(restore_snapshot(&mut saved, &snapshot));
+        assert_eq!(
+            saved.get::<VerletObject>(player).unwrap().position_current,
+            Vec2::new(0.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn bodies_spawned_after_startup_are_restored() {
+        let mut world = World::new();
+        body(&mut world, Vec2::ZERO);
+        assign_snapshot_ids(&mut world);
+        let late = body(&mut world, Vec2::new(30.0, 0.0));
+        let snapshot = capture_snapshot(&mut world);
+        assert!(world.get::<SnapshotId>(late).is_some());
+
+        world
+            .get_mut::<VerletObject>(late)
+            .unwrap()
+            .position_current = Vec2::new(90.0, 0.0);
+        assert!(restore_snapshot(&mut world, &snapshot));
+        assert_eq!(
+            world.get::<VerletObject>(late).unwrap().position_current,
+            Vec2::new(30.0, 0.0)
+        );
+    }
+}
@@ -1,12 +1,12 @@
 use crate::color_picker::{ColorPickState, GlobalColor};
-use crate::physics::{PhysicsSet, TrackCollision};
+use crate::physics::{PhysicsSet, TriggerEntered, TriggerExited};
 use crate::{CursorModeRes, Player};
 use bevy::app::{App, FixedPreUpdate, FixedUpdate, Plugin, Startup, Update};
 use bevy::color::Color;
 use bevy::prelude::{
-    default, BuildChildren, Button, Changed, ChildBuild, Commands, Component, Entity, Interaction,
-    IntoSystemConfigs, JustifyContent, Label, Node, OnEnter, Query, Res, ResMut, Resource, Text,
-    TextColor, TextFont, Time, Val, Visibility, Window, With, Without,
+    default, BuildChildren, Button, Changed, ChildBuild, Commands, Component, Entity, EventReader,
+    Interaction, IntoSystemConfigs, JustifyContent, Label, Node, OnEnter, Query, Res, ResMut,
+    Resource, Text, TextColor, TextFont, Time, Val, Visibility, Window, With, Without,
 };
 use bevy::text::cosmic_text::Action;
 use bevy::ui::{AlignContent, BackgroundColor, FlexDirection, UiRect};
@@ -138,28 +138,41 @@ fn display_time(
 }
 
 fn stop_start_tracking(
-    player_tracker_query: Query<(&TrackCollision), With<Player>>,
+    mut entered_events: EventReader<TriggerEntered>,
+    mut exited_events: EventReader<TriggerExited>,
+    player_query: Query<Entity, With<Player>>,
     mut time_tracker: ResMut<TimeTracker>,
     timer_starter_query: Query<&TimerStarter>,
     finish_query: Query<&Finish>,
     mut commands: Commands,
     global_color: Res<GlobalColor>,
 ) {
-    for (collision_tracker) in player_tracker_query.iter() {
-        for trigger in &collision_tracker.triggers {
-            if let Ok(timer_starter) = timer_starter_query.get(*trigger) {
-                time_tracker.active.set(true);
-                time_tracker.time = 0.0;
-            }
+    for entered in entered_events.read() {
+        if (!player_query.contains(entered.entity)) {
+            continue;
+        }
+        if let Ok(timer_starter) = timer_starter_query.get(entered.trigger) {
+            time_tracker.active.set(true);
+            time_tracker.time = 0.0;
+        }
 
-            if let Ok(finish) = finish_query.get(*trigger) {
-                if (time_tracker.active.value) {
-                    time_tracker.show_finish_screen.set(true);
-                }
-                time_tracker.active.set(false);
-                time_tracker.finish_time = time_tracker.time;
-                time_tracker.time = 0.0;
+        if let Ok(finish) = finish_query.get(entered.trigger) {
+            if (time_tracker.active.value) {
+                time_tracker.show_finish_screen.set(true);
             }
+            time_tracker.active.set(false);
+            time_tracker.finish_time = time_tracker.time;
+            time_tracker.time = 0.0;
+        }
+    }
+
+    //the clock only starts running once the player leaves the start
+    for exited in exited_events.read() {
+        if (!player_query.contains(exited.entity)) {
+            continue;
+        }
+        if let Ok(timer_starter) = timer_starter_query.get(exited.trigger) {
+            time_tracker.time = 0.0;
         }
     }
 }
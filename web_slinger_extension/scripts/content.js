@@ -112,6 +112,159 @@ function parseColor(input) {
     return { r: Number(arr[0]), g: Number(arr[1]), b: Number(arr[2]), a: Number(arr[3]) };
 }
 
+//characters with at least minFontSize get a coarse bitmap of their glyph instead of a plain box
+const GLYPH_OUTLINES = { enabled: true, minFontSize: 40, cellSize: 4 };
+const glyphCanvas = document.createElement("canvas");
+
+function glyphMask(char, style, rect) {
+    if (!GLYPH_OUTLINES.enabled || parseFloat(style.fontSize) < GLYPH_OUTLINES.minFontSize) {
+        return null;
+    }
+    const width = Math.ceil(rect.width);
+    const height = Math.ceil(rect.height);
+    const columns = Math.max(1, Math.round(width / GLYPH_OUTLINES.cellSize));
+    const rows = Math.max(1, Math.round(height / GLYPH_OUTLINES.cellSize));
+    glyphCanvas.width = width;
+    glyphCanvas.height = height;
+    const ctx = glyphCanvas.getContext("2d", { willReadFrequently: true });
+    ctx.clearRect(0, 0, width, height);
+    ctx.font = `${style.fontStyle} ${style.fontWeight} ${style.fontSize} ${style.fontFamily}`;
+    ctx.textBaseline = "alphabetic";
+    ctx.fillText(char, 0, ctx.measureText(char).fontBoundingBoxAscent);
+    const alpha = ctx.getImageData(0, 0, width, height).data;
+
+    //a cell is solid when more than a quarter of its pixels are covered, so single strokes survive
+    const cells = [];
+    for (let row = 0; row < rows; row++) {
+        for (let column = 0; column < columns; column++) {
+            const x0 = Math.floor(column * width / columns);
+            const x1 = Math.floor((column + 1) * width / columns);
+            const y0 = Math.floor(row * height / rows);
+            const y1 = Math.floor((row + 1) * height / rows);
+            let covered = 0;
+            for (let y = y0; y < y1; y++) {
+                for (let x = x0; x < x1; x++) {
+                    if (alpha[(y * width + x) * 4 + 3] > 127) covered++;
+                }
+            }
+            cells.push(covered * 4 > (x1 - x0) * (y1 - y0) ? 1 : 0);
+        }
+    }
+    return { columns, rows, cells };
+}
+
+//running text can be jumped through from below, links and headings stay solid
+const ONE_WAY_TEXT = 'p, li, blockquote, dd, td';
+
+//pages opt sections into water with a class or data attribute, colors alone are too easy to mistake
+const WATER_SELECTOR = '.web-slinger-water, [data-web-slinger="water"]';
+
+function isWater(el) {
+    return el.matches(WATER_SELECTOR);
+}
+
+//clockwise css rotation of the element including its ancestors, in radians
+function elementRotation(el) {
+    let angle = 0;
+    for (let e = el; e && e !== document.documentElement; e = e.parentElement) {
+        const transform = getComputedStyle(e).transform;
+        if (transform && transform.startsWith("matrix(")) {
+            const values = transform.split("(")[1].split(")")[0].split(",").map(Number);
+            angle += Math.atan2(values[1], values[0]);
+        }
+    }
+    return angle;
+}
+
+//transforms don't change layout, so the unrotated size is measured with them switched off
+function unrotatedSize(el, measure) {
+    const transformed = [];
+    for (let e = el; e && e !== document.documentElement; e = e.parentElement) {
+        if (getComputedStyle(e).transform !== "none") {
+            transformed.push([e, e.style.transform]);
+            e.style.transform = "none";
+        }
+    }
+    const rect = measure();
+    transformed.forEach(([e, transform]) => e.style.transform = transform);
+    return { width: rect.width, height: rect.height };
+}
+
+//the rotated bounding rect keeps its center, the edges are replaced by the unrotated size
+function colliderRect(rect, el, measure, scrollbarWidth) {
+    const rotation = elementRotation(el);
+    const centerX = (rect.left + rect.right) / 2 + scrollbarWidth + window.scrollX;
+    const centerY = (rect.top + rect.bottom) / 2 + window.scrollY;
+    const size = rotation === 0 ? rect : unrotatedSize(el, measure);
+    return {
+        top: centerY - size.height / 2,
+        bottom: centerY + size.height / 2,
+        right: centerX + size.width / 2,
+        left: centerX - size.width / 2,
+        rotation: rotation,
+    };
+}
+
+//elements that move on their own, their colliders follow them around
+const trackedElements = [];
+//ids of the tracked elements, kept off the DOM so the page never sees them
+const trackedElementIds = new WeakMap();
+
+//transitions of these properties move the element, color or opacity fades don't
+const MOVING_PROPERTIES = ['transform', 'translate', 'left', 'top', 'all'];
+//computed once per element, every character of a text node asks for all of its ancestors
+const animatedCache = new WeakMap();
+
+function isAnimated(el) {
+    if (animatedCache.has(el)) {
+        return animatedCache.get(el);
+    }
+    const style = getComputedStyle(el);
+    //the duration list repeats when it is shorter than the property list
+    const durations = style.transitionDuration.split(",").map(d => parseFloat(d));
+    const hasTransition = style.transitionProperty.split(",").some((property, i) =>
+        MOVING_PROPERTIES.includes(property.trim()) && durations[i % durations.length] > 0);
+    const hasAnimation = style.animationName !== "none";
+    const isCarousel = /carousel|slider|swiper|marquee/i.test(el.className) || el.tagName === "MARQUEE";
+    const animated = hasTransition || hasAnimation || isCarousel;
+    animatedCache.set(el, animated);
+    return animated;
+}
+
+function animatedElementInfo(el, scrollbarWidth) {
+    let animated = el;
+    while (animated && animated !== document.body && !isAnimated(animated)) {
+        animated = animated.parentElement;
+    }
+    if (!animated || animated === document.body) {
+        return null;
+    }
+    if (!trackedElementIds.has(animated)) {
+        trackedElementIds.set(animated, trackedElements.length);
+        trackedElements.push({ el: animated, left: null, top: null });
+    }
+    const rect = animated.getBoundingClientRect();
+    return {
+        id: trackedElementIds.get(animated),
+        left: rect.left + scrollbarWidth + window.scrollX,
+        top: rect.top + window.scrollY,
+    };
+}
+
+function trackElements(wasm) {
+    const scrollbarWidth = window.innerWidth - document.documentElement.clientWidth;
+    trackedElements.forEach((tracked, id) => {
+        const rect = tracked.el.getBoundingClientRect();
+        const left = rect.left + scrollbarWidth + window.scrollX;
+        const top = rect.top + window.scrollY;
+        if (left !== tracked.left || top !== tracked.top) {
+            tracked.left = left;
+            tracked.top = top;
+            wasm.set_element_pos(id, left, top);
+        }
+    });
+    requestAnimationFrame(() => trackElements(wasm));
+}
 
 (async () => {
 
@@ -125,6 +278,7 @@ function parseColor(input) {
 
         const textNodes = [];
         const others = [];
+        const waterElements = [];
 
         document.querySelectorAll('*').forEach(el => {
             // Ignore non-visible elements
@@ -136,6 +290,10 @@ function parseColor(input) {
                 style.display !== 'none';
             const isBehind = isBehindOtherElement(el);
 
+            if (!isBehind && isVisible && isWater(el)) {
+                waterElements.push(el);
+            }
+
             if (!isBehind && isVisible && !['STYLE', 'SCRIPT', 'META', 'LINK', 'NOSCRIPT'].includes(el.tagName)) {
                 el.childNodes.forEach(node => {
                     if (node.nodeType === Node.TEXT_NODE && node.nodeValue.trim()) {
@@ -164,7 +322,11 @@ function parseColor(input) {
             if (text && text.trim() && text.trim().length > 0) {
                 const color_string = getComputedStyle(node.parentElement).color;
                 const color_values = parseColor(color_string);
-                const fontWeight = getComputedStyle(node.parentElement).fontWeight;
+                const parentStyle = getComputedStyle(node.parentElement);
+                const fontWeight = parentStyle.fontWeight;
+                const tagElement = node.parentElement.closest('a, h1, h2, h3') || node.parentElement;
+                const element = animatedElementInfo(node.parentElement, scrollbarWidth);
+                const oneWay = !node.parentElement.closest('a, h1, h2, h3') && !!node.parentElement.closest(ONE_WAY_TEXT);
 
                 for (let i = 0; i < text.length; i++) {
                     let char = text[i];
@@ -175,12 +337,13 @@ function parseColor(input) {
                     // rects.push(rect);
                     if (rect.width > 0 && rect.height > 0) { // Ensure valid rectangles
                         colliders.push({
-                            top: rect.top + window.scrollY,
-                            bottom: rect.bottom + window.scrollY,
-                            right: rect.right + scrollbarWidth + window.scrollX,
-                            left: rect.left + scrollbarWidth + window.scrollX,
+                            ...colliderRect(rect, node.parentElement, () => range.getBoundingClientRect(), scrollbarWidth),
                             letter: char,
                             color: color_values,
+                            tag: tagElement.tagName,
+                            one_way: oneWay,
+                            element: element,
+                            mask: glyphMask(char, parentStyle, rect),
                         });
                     }
                     // colliders.push({ top: rect.top, bottom: rect.bottom, right: rect.right, left: rect.left });
@@ -193,21 +356,33 @@ function parseColor(input) {
             let letter = el.alt;
             if (letter && (letter.length == 1 || letter.length == 2)) {
                 const color_values = { r: 100, g: 10, b: 75, a: 255 };
-                const rect = el.getBoundingClientRect();
+                //the water marker floods the block it is placed in
+                const rect = letter === "🌊" && el.parentElement
+                    ? el.parentElement.getBoundingClientRect()
+                    : el.getBoundingClientRect();
                 if (rect.width > 0 && rect.height > 0) { // Ensure valid rectangles
                     colliders.push({
-                        top: rect.top + window.scrollY,
-                        bottom: rect.bottom + window.scrollY,
-                        right: rect.right + scrollbarWidth + window.scrollX,
-                        left: rect.left + scrollbarWidth + window.scrollX,
+                        ...colliderRect(rect, el, () => el.getBoundingClientRect(), scrollbarWidth),
                         letter: letter,
                         color: color_values,
+                        tag: el.tagName,
+                        element: animatedElementInfo(el, scrollbarWidth),
                     });
                 }
             }
 
         })
 
+        waterElements.forEach(el => {
+            const rect = el.getBoundingClientRect();
+            colliders.push({
+                ...colliderRect(rect, el, () => el.getBoundingClientRect(), scrollbarWidth),
+                letter: "🌊",
+                color: parseColor(getComputedStyle(el).backgroundColor),
+                tag: el.tagName,
+            });
+        });
+
         gameScreen.style.display = originalDisplay;
         window.scrollTo({ left: originalScrollX, top: originalScrollY, behavior: 'instant' });
         // window.scrollTo(originalScrollX, originalScrollY);
@@ -234,6 +409,11 @@ function parseColor(input) {
     const waitForWasm = setInterval(() => {
         if (module.wasm) {
             module.wasm.set_scroll_pos(window.scrollY, window.scrollX);
+            trackElements(module.wasm);
+            //collider outlines, broadphase, contacts, sticks and rays, also toggled with F3 in game
+            window.set_physics_debug = function (enabled) {
+                module.wasm.set_physics_debug(enabled);
+            };
             clearInterval(waitForWasm); // Stop checking once it's called
         }
     }, 10);